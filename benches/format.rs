@@ -0,0 +1,77 @@
+// 一个 /8 (16777216个地址)的生成和点分十进制格式化
+// to_string 为原来每个地址先格式化成 String 的写法，lookup_table 为现在的查表写法
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use generate_all_ipv4_addresses::dotted;
+use generate_all_ipv4_addresses::generate::{generate_ips, RecordSet};
+use generate_all_ipv4_addresses::input;
+use generate_all_ipv4_addresses::output::{OutputFormat, RecordFormat};
+use std::net::Ipv4Addr;
+
+const NETWORK: &str = "10.0.0.0/8";
+const START: u32 = 10 << 24;
+const COUNT: u32 = 1 << 24;
+
+// 缓冲区满 60KB 就清空(相当于写出)，之后重复使用，只测量格式化本身
+fn flush_if_full(buffer: &mut Vec<u8>) {
+    if buffer.len() >= 60 * 1024 {
+        black_box(&buffer[..]);
+        buffer.clear();
+    }
+}
+
+fn format_slash8(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format_slash8");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(COUNT as u64));
+
+    group.bench_function("to_string", |b| {
+        let mut buffer = Vec::with_capacity(64 * 1024);
+        b.iter(|| {
+            for ip in START..START + COUNT {
+                buffer.extend_from_slice(Ipv4Addr::from(ip).to_string().as_bytes());
+                buffer.push(b'\n');
+                flush_if_full(&mut buffer);
+            }
+        })
+    });
+
+    group.bench_function("lookup_table", |b| {
+        let mut buffer = Vec::with_capacity(64 * 1024);
+        b.iter(|| {
+            for ip in START..START + COUNT {
+                dotted::push_ipv4(&mut buffer, ip);
+                buffer.push(b'\n');
+                flush_if_full(&mut buffer);
+            }
+        })
+    });
+    group.finish();
+}
+
+// 生成和按纯文本格式编码的完整路径
+fn generate_and_encode_slash8(c: &mut Criterion) {
+    let input = input::from_cidrs(&[NETWORK.to_string()]).expect("CIDR格式正确");
+    let format = RecordFormat::new(OutputFormat::Plain, &input, &[]).expect("没有标签列");
+    let mut group = c.benchmark_group("slash8");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(COUNT as u64));
+
+    group.bench_function("generate", |b| {
+        b.iter(|| black_box(generate_ips(&input, &[], 1, None).expect("生成失败").len()))
+    });
+
+    let ips = generate_ips(&input, &[], 1, None).expect("生成失败");
+    group.bench_function("encode_plain", |b| {
+        let mut buffer = Vec::with_capacity(64 * 1024);
+        b.iter(|| {
+            for record in RecordSet::new(&ips, &[]).iter() {
+                format.encode(&record, &mut buffer);
+                flush_if_full(&mut buffer);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, format_slash8, generate_and_encode_slash8);
+criterion_main!(benches);
@@ -0,0 +1,219 @@
+// 从读取输入到写出文件的各个阶段，每个阶段都在三种输入规模上测量
+// 输入为 benches/fixtures 中固定的 CSV 文件(small 4096个、medium 65536个、large 1048576个地址)，
+// 列为 cidr,provider,region，网络的前缀长度各不相同，每隔几行留出空隙，内容不会随运行变化
+// 写文件的测试写入系统临时目录，结束后删除
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use generate_all_ipv4_addresses::cli::{Options, SplitMode};
+use generate_all_ipv4_addresses::generate::{generate_ips, GeneratedIp, RecordSet};
+use generate_all_ipv4_addresses::input::{self, InputFormat, InputSet};
+use generate_all_ipv4_addresses::manifest::Manifest;
+use generate_all_ipv4_addresses::naming::FileNaming;
+use generate_all_ipv4_addresses::output::{self, OutputFormat, RecordFormat};
+use generate_all_ipv4_addresses::progress::Progress;
+use generate_all_ipv4_addresses::template::Template;
+use generate_all_ipv4_addresses::{parallel, split, write};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::time::Duration;
+
+const SIZES: &[&str] = &["small", "medium", "large"];
+
+const TEXT_FORMATS: &[(&str, OutputFormat)] = &[
+    ("plain", OutputFormat::Plain),
+    ("csv", OutputFormat::Csv),
+    ("jsonl", OutputFormat::JsonLines),
+    ("int", OutputFormat::Integer),
+    ("hex", OutputFormat::Hex),
+    ("binary", OutputFormat::Binary),
+    ("reverse", OutputFormat::Reverse),
+];
+
+const SPLIT_MODES: &[&str] = &[
+    "all",
+    "equal:16",
+    "lines:10000",
+    "lines:10000/24",
+    "bytes:256K",
+    "label:provider",
+    "cidr",
+    "prefix:16",
+];
+
+fn fixture_path(size: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("benches")
+        .join("fixtures")
+        .join(format!("{}.csv", size))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load_fixture(size: &str) -> InputSet {
+    input::read_input(&fixture_path(size), InputFormat::Csv, "cidr").expect("读取基准测试的输入失败")
+}
+
+fn generate(input: &InputSet) -> Vec<GeneratedIp> {
+    generate_ips(input, &[], 1, None).expect("生成失败")
+}
+
+// 每次测试使用单独的空目录
+fn bench_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("generate_all_ipv4_addresses_bench").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("创建临时目录失败");
+    dir
+}
+
+// 把全部记录写入丢弃数据的 io::sink
+fn write_all(records: &RecordSet, format: &RecordFormat) {
+    output::write_records(&mut io::sink(), records, slice::from_ref(&(0..records.len())), format, true, None).expect("写入失败");
+}
+
+fn configure<'a>(c: &'a mut Criterion, name: &str) -> BenchmarkGroup<'a, WallTime> {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.warm_up_time(Duration::from_secs(1));
+    group.measurement_time(Duration::from_secs(5));
+    group
+}
+
+// 读取并解析输入文件
+fn parse(c: &mut Criterion) {
+    let mut group = configure(c, "parse");
+    for &size in SIZES {
+        let rows = load_fixture(size).records.len();
+        group.throughput(Throughput::Elements(rows as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &fixture_path(size), |b, path| {
+            b.iter(|| black_box(input::read_input(path, InputFormat::Csv, "cidr").expect("解析失败").records.len()))
+        });
+    }
+    group.finish();
+}
+
+// 展开全部地址，分别使用单线程和全部CPU核心
+fn enumerate(c: &mut Criterion) {
+    let mut group = configure(c, "enumerate");
+    let threads = [("1_thread", 1), ("all_threads", parallel::default_threads())];
+    for &size in SIZES {
+        let input = load_fixture(size);
+        group.throughput(Throughput::Elements(generate(&input).len() as u64));
+        for (name, threads) in threads {
+            group.bench_with_input(BenchmarkId::new(name, size), &input, |b, input| {
+                b.iter(|| black_box(generate_ips(input, &[], threads, None).expect("生成失败").len()))
+            });
+        }
+    }
+    group.finish();
+}
+
+// 把全部记录按文本格式编码，写入丢弃数据的 io::sink，只测量格式化本身
+fn format_text(c: &mut Criterion) {
+    let mut group = configure(c, "format");
+    for &size in SIZES {
+        let input = load_fixture(size);
+        let ips = generate(&input);
+        let records = RecordSet::new(&ips, &[]);
+        group.throughput(Throughput::Elements(records.len() as u64));
+        for &(name, kind) in TEXT_FORMATS {
+            let format = RecordFormat::new(kind, &input, &[]).expect("没有标签列");
+            group.bench_with_input(BenchmarkId::new(name, size), &records, |b, records| {
+                b.iter(|| write_all(records, &format))
+            });
+        }
+        let mut format = RecordFormat::new(OutputFormat::Plain, &input, &[]).expect("没有标签列");
+        format.template = Some(Template::parse("https://{ip}/{provider}?n={index}", &input).expect("模板正确"));
+        group.bench_with_input(BenchmarkId::new("template", size), &records, |b, records| {
+            b.iter(|| write_all(records, &format))
+        });
+    }
+    group.finish();
+}
+
+// 编码同样的记录，分别使用1个、2个、4个线程和全部CPU核心，比较多线程编码的加速
+fn encode_threads(c: &mut Criterion) {
+    let mut group = configure(c, "encode_threads");
+    let mut threads = vec![1, 2, 4, parallel::default_threads()];
+    threads.sort_unstable();
+    threads.dedup();
+    for &size in SIZES {
+        let input = load_fixture(size);
+        let ips = generate(&input);
+        let records = RecordSet::new(&ips, &[]);
+        group.throughput(Throughput::Elements(records.len() as u64));
+        for (name, kind) in [("plain", OutputFormat::Plain), ("csv", OutputFormat::Csv), ("jsonl", OutputFormat::JsonLines)] {
+            for &count in &threads {
+                let mut format = RecordFormat::new(kind, &input, &[]).expect("没有标签列");
+                format.threads = count;
+                let id = BenchmarkId::new(format!("{}/{}_threads", name, count), size);
+                group.bench_with_input(id, &records, |b, records| b.iter(|| write_all(records, &format)));
+            }
+        }
+    }
+    group.finish();
+}
+
+// SQLite 和 Parquet 需要写入真实的文件，与程序一样通过 write::write_ips_to_file 写入
+// 每次使用新的清单，文件不会被当作追加而接着写入
+fn format_table(c: &mut Criterion) {
+    let mut group = configure(c, "format_table");
+    let dir = bench_dir("format_table");
+    let progress = Progress::new(false);
+    for &size in SIZES {
+        let input = load_fixture(size);
+        let ips = generate(&input);
+        let records = RecordSet::new(&ips, &[]);
+        group.throughput(Throughput::Elements(records.len() as u64));
+        for (name, kind) in [("sqlite", OutputFormat::Sqlite), ("parquet", OutputFormat::Parquet)] {
+            let format = RecordFormat::new(kind, &input, &[]).expect("没有标签列");
+            let path = dir.join(format!("ip.{}", kind.extension()));
+            group.bench_with_input(BenchmarkId::new(name, size), &records, |b, records| {
+                b.iter(|| {
+                    let mut manifest = Manifest::default();
+                    write::write_ips_to_file(&path, records, slice::from_ref(&(0..records.len())), &format, &mut manifest, &progress)
+                        .expect("写入失败")
+                })
+            });
+        }
+    }
+    group.finish();
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// 每种分割模式的计划和写入(纯文本格式)，与程序使用相同的函数：
+// split::plan_files 计算计划，write::write_plan 逐个写入临时文件、计算校验和并改名，记入清单
+fn split_modes(c: &mut Criterion) {
+    let mut group = configure(c, "split");
+    let dir = bench_dir("split");
+    let progress = Progress::new(false);
+    for &size in SIZES {
+        let input = load_fixture(size);
+        let ips = generate(&input);
+        let records = RecordSet::new(&ips, &[]);
+        let format = RecordFormat::new(OutputFormat::Plain, &input, &[]).expect("没有标签列");
+        let naming = FileNaming::new(&dir.to_string_lossy(), "ip_{index}.{ext}", &input, None, None, "txt")
+            .expect("文件名模板正确");
+        group.throughput(Throughput::Elements(records.len() as u64));
+        for &text in SPLIT_MODES {
+            let options = Options {
+                split: SplitMode::parse(text).expect("分割模式正确"),
+                ..Options::default()
+            };
+            group.bench_with_input(BenchmarkId::new(text, size), &records, |b, records| {
+                b.iter(|| {
+                    let (plans, _) = split::plan_files(records, &format, &options.split).expect("分割失败");
+                    let paths = write::file_paths(records, &plans, &format, &naming, &options, 0).expect("路径冲突");
+                    let mut manifest = Manifest::default();
+                    assert!(write::write_plan(records, &plans, &paths, &format, &mut manifest, &progress));
+                })
+            });
+        }
+    }
+    group.finish();
+    let _ = fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, parse, enumerate, format_text, encode_threads, format_table, split_modes);
+criterion_main!(benches);
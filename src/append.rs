@@ -0,0 +1,114 @@
+use crate::cli::{Options, SplitMode};
+use crate::input::{InputRecord, InputSet};
+use crate::manifest::Manifest;
+use crate::ranges::{self, Range4};
+use crate::template::Template;
+use ipnetwork::IpNetwork;
+use std::io;
+
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+// 检查已有的输出能否追加：清单必须完整，影响每行内容的参数必须与已有输出相同
+pub fn check_existing(existing: &Manifest, options: &Options) -> io::Result<()> {
+    if !existing.complete {
+        return Err(invalid("已有输出的清单标记为未完成，请重新生成后再追加".to_string()));
+    }
+    let previous: Options = serde_json::from_value(existing.parameters.clone()).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("清单中的运行参数无法解析：{}", e))
+    })?;
+    let differences = [
+        ("--format", previous.format != options.format),
+        ("--header", previous.header != options.header),
+        ("--separator", previous.separator != options.separator),
+        ("--line-ending", previous.line_ending != options.line_ending),
+        ("--ports", previous.ports != options.ports),
+        ("--template", previous.template != options.template),
+        ("--with-labels", previous.with_labels != options.with_labels),
+    ];
+    let changed: Vec<&str> = differences
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| *name)
+        .collect();
+    if !changed.is_empty() {
+        return Err(invalid(format!("追加时 {} 必须与已有输出相同", changed.join("、"))));
+    }
+    Ok(())
+}
+
+// 按顺序合并时会重新写入已有的地址，但清单中没有保存它们的标签
+pub fn check_sorted(options: &Options, template: Option<&Template>) -> io::Result<()> {
+    let uses_labels = !options.with_labels.is_empty()
+        || template.is_some_and(|template| template.uses_labels())
+        || matches!(options.split, SplitMode::Label(_));
+    if uses_labels {
+        return Err(invalid(
+            "--sorted 会重新写入已有的地址，无法恢复它们的标签；不能与 --with-labels、带标签列的模板或 label 分割一起使用"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// 网络对应的区间，已排序、已合并，忽略IPv6
+fn v4_ranges<I: IntoIterator<Item = IpNetwork>>(networks: I) -> Vec<Range4> {
+    let ranges = networks
+        .into_iter()
+        .filter_map(|network| match network {
+            IpNetwork::V4(network) => Some(ranges::network_range(network)),
+            IpNetwork::V6(_) => None,
+        })
+        .collect();
+    ranges::merge(ranges)
+}
+
+// 已有输出覆盖的地址区间：按清单中记录的CIDR和排除地址计算，不需要逐行读取输出文件
+pub fn covered_ranges(existing: &Manifest) -> io::Result<Vec<Range4>> {
+    let covered = v4_ranges(existing.input.parse_networks()?);
+    Ok(ranges::difference(&covered, &existing.input.parse_excluded()?))
+}
+
+// 追加后的清单中记录的排除地址：已有输出和这次追加都没有写入的地址
+pub fn merged_exclusions(
+    existing: &Manifest,
+    input: &InputSet,
+    excluded: &[Range4],
+) -> io::Result<Vec<Range4>> {
+    let existing_networks = v4_ranges(existing.input.parse_networks()?);
+    let new_networks = v4_ranges(input.records.iter().map(|record| record.network));
+    let written = ranges::merge(
+        [
+            ranges::difference(&existing_networks, &existing.input.parse_excluded()?),
+            ranges::difference(&new_networks, excluded),
+        ]
+        .concat(),
+    );
+    let all = ranges::merge([existing_networks, new_networks].concat());
+    Ok(ranges::difference(&all, &written))
+}
+
+// 按顺序合并时使用的输入：清单中已有的CIDR在前(没有标签)，新的输入在后
+pub fn combined_input(existing: &Manifest, input: &InputSet) -> io::Result<InputSet> {
+    let mut records: Vec<InputRecord> = existing
+        .input
+        .parse_networks()?
+        .into_iter()
+        .map(|network| InputRecord { network, labels: Vec::new() })
+        .collect();
+    records.extend(input.records.iter().cloned());
+    Ok(InputSet { label_names: input.label_names.clone(), records })
+}
+
+// 追加后清单中的CIDR：已有的在前，新的CIDR按首次出现的顺序接在后面
+pub fn merge_networks(existing: &[String], added: &[String]) -> Vec<String> {
+    let mut networks = existing.to_vec();
+    for network in added {
+        if !networks.contains(network) {
+            networks.push(network.clone());
+        }
+    }
+    networks
+}
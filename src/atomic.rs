@@ -0,0 +1,85 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+
+// 写入中的文件使用的临时文件名：同一目录下的 .<文件名>.partial
+pub fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.partial", name))
+}
+
+// 先写入同一目录下的临时文件，commit 时同步到磁盘再改名为目标文件；
+// 没有 commit 就被丢弃(出错或中途退出)时删除临时文件，目标文件不会只写了一半
+pub struct AtomicFile {
+    file: Option<File>,
+    temp: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<AtomicFile> {
+        let temp = temp_path(path);
+        let file = File::create(&temp)?;
+        Ok(AtomicFile { file: Some(file), temp, path: path.to_path_buf(), committed: false })
+    }
+
+    // 同步文件内容并改名，改名后同步所在目录，保证改名本身也已落盘
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.sync_all()?;
+        }
+        fs::rename(&self.temp, &self.path)?;
+        self.committed = true;
+        sync_dir(&self.path)
+    }
+
+    fn file(&mut self) -> &mut File {
+        self.file.as_mut().expect("文件已经提交")
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.file.take();
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+// 以原子方式写入整个文件
+pub fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents)?;
+    file.commit()
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+// 其他平台无法打开目录同步，改名由文件系统保证
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
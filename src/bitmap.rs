@@ -0,0 +1,100 @@
+use crate::atomic;
+use crate::ranges::Range4;
+use roaring::RoaringBitmap;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+
+// 地址集合文件默认的扩展名
+pub const EXTENSION: &str = "roaring";
+
+// IPv4地址集合，内部是覆盖整个u32空间的 roaring 位图
+// 连续的地址压缩成区间(run)存放，整段的CIDR只占几个字节；
+// 文件使用 roaring 的标准序列化格式，其他语言的 roaring 库也能直接读取
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressSet {
+    bitmap: RoaringBitmap,
+}
+
+impl AddressSet {
+    // 由已排序、已合并的区间建立集合，整段插入，不逐个地址处理
+    pub fn from_ranges(ranges: &[Range4]) -> AddressSet {
+        let mut bitmap = RoaringBitmap::new();
+        for &(start, end) in ranges {
+            bitmap.insert_range(start..=end);
+        }
+        bitmap.optimize();
+        AddressSet { bitmap }
+    }
+
+    pub fn load(path: &Path) -> io::Result<AddressSet> {
+        let reader = BufReader::new(File::open(path)?);
+        let bitmap = RoaringBitmap::deserialize_from(reader).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} 不是有效的地址集合文件：{}", path.display(), err),
+            )
+        })?;
+        Ok(AddressSet { bitmap })
+    }
+
+    // 以原子方式写入文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        atomic::write(path, &self.to_bytes()?)
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.bitmap.serialize_into(out)
+    }
+
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.bitmap.serialized_size());
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // 序列化后的字节数
+    pub fn serialized_size(&self) -> usize {
+        self.bitmap.serialized_size()
+    }
+
+    pub fn union(&mut self, other: &AddressSet) {
+        self.bitmap |= &other.bitmap;
+        self.bitmap.optimize();
+    }
+
+    pub fn intersect(&mut self, other: &AddressSet) {
+        self.bitmap &= &other.bitmap;
+        self.bitmap.optimize();
+    }
+
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        self.bitmap.contains(u32::from(ip))
+    }
+
+    // 集合中的地址数
+    pub fn count(&self) -> u64 {
+        self.bitmap.len()
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.bitmap.iter().map(Ipv4Addr::from)
+    }
+
+    // 把连续的地址合并成区间，按地址排序，可以再用 ranges::to_networks 转成CIDR
+    pub fn ranges(&self) -> Vec<Range4> {
+        let mut ranges: Vec<Range4> = Vec::new();
+        let mut iter = self.bitmap.iter();
+        // 一段连续的地址跨越多个容器时会分成几段返回，这里再接起来
+        while let Some(run) = iter.next_range() {
+            let (start, end) = (*run.start(), *run.end());
+            match ranges.last_mut() {
+                Some((_, last)) if *last as u64 + 1 == start as u64 => *last = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+        ranges
+    }
+}
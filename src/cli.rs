@@ -0,0 +1,578 @@
+use crate::export::ExportTarget;
+use crate::input::InputFormat;
+use crate::bitmap;
+use crate::columnar;
+use crate::output::{self, OutputFormat};
+use crate::parallel;
+use crate::ports;
+use serde::{Deserialize, Serialize};
+
+
+// 作为输出文件名时表示写入标准输出
+pub const STDOUT: &str = "-";
+
+// 要执行的操作
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Command {
+    #[default]
+    Generate, // 生成IP地址并写入文件
+    Verify,   // 按清单校验输出文件
+    Zones,    // 列出输入对应的反向区
+    Export,   // 把合并后的CIDR导出成防火墙等工具的格式
+    Set,      // 建立、合并和查询地址集合文件
+}
+
+// set 子命令的操作
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SetOperation {
+    #[default]
+    Build,     // 由输入的CIDR(去掉排除的地址)建立集合
+    Union,     // 多个集合的并集
+    Intersect, // 多个集合的交集
+    Contains,  // 查询地址是否在集合中
+    Cidrs,     // 把集合转换回最少的CIDR
+    Text,      // 逐个列出集合中的地址
+}
+
+impl SetOperation {
+    pub fn from_name(name: &str) -> Option<SetOperation> {
+        match name {
+            "build" => Some(SetOperation::Build),
+            "union" => Some(SetOperation::Union),
+            "intersect" | "intersection" => Some(SetOperation::Intersect),
+            "contains" => Some(SetOperation::Contains),
+            "cidrs" | "cidr" => Some(SetOperation::Cidrs),
+            "text" => Some(SetOperation::Text),
+            _ => None,
+        }
+    }
+}
+
+// 写入txt文件的模式
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    Equal(usize),    // 等份分割
+    MaxLines(usize), // 每个文件的最大行数
+    MaxBytes(u64),   // 每个文件的最大字节数
+    All,             // 全部写入一个文件
+    Label(String),   // 按标签列的值分文件
+    Cidr,            // 每个输入CIDR一个文件
+    Prefix(u8),      // 每个 /N 网段一个文件
+    WholeBlocks { max_lines: usize, prefix: u8 }, // 限制每个文件的行数，但不拆开 /N 网段
+}
+
+impl SplitMode {
+    // 解析 equal:4、lines:10000、lines:10000/24、bytes:100M、label:provider、cidr、prefix:16、all 这样的写法
+    pub fn parse(text: &str) -> Result<SplitMode, String> {
+        let (kind, value) = match text.split_once(':') {
+            Some((kind, value)) => (kind, Some(value)),
+            None => (text, None),
+        };
+        let number = |value: Option<&str>| -> Result<usize, String> {
+            value
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .ok_or_else(|| format!("分割模式 {} 需要一个数字，例如 {}:10", text, kind))
+        };
+        match kind {
+            "equal" => match number(value)? {
+                0 => Err("等份分割的份数必须大于0".to_string()),
+                num_segments => Ok(SplitMode::Equal(num_segments)),
+            },
+            "lines" => {
+                let (lines, prefix) = match value.and_then(|v| v.split_once('/')) {
+                    Some((lines, prefix)) => (Some(lines), Some(parse_prefix(prefix)?)),
+                    None => (value, None),
+                };
+                let max_lines = number(lines)?;
+                if max_lines == 0 {
+                    return Err("每个文件的最多写入行数必须大于0".to_string());
+                }
+                match prefix {
+                    Some(prefix) => Ok(SplitMode::WholeBlocks { max_lines, prefix }),
+                    None => Ok(SplitMode::MaxLines(max_lines)),
+                }
+            }
+            "bytes" => match value.map(parse_size) {
+                Some(Some(max_bytes)) if max_bytes > 0 => Ok(SplitMode::MaxBytes(max_bytes)),
+                _ => Err(format!("分割模式 {} 需要大于0的大小，例如 bytes:100M", text)),
+            },
+            "all" => Ok(SplitMode::All),
+            "cidr" => Ok(SplitMode::Cidr),
+            "prefix" => match value {
+                Some(prefix) => Ok(SplitMode::Prefix(parse_prefix(prefix)?)),
+                None => Err("分割模式 prefix 需要前缀长度，例如 prefix:16".to_string()),
+            },
+            "label" => match value {
+                Some(name) if !name.is_empty() => Ok(SplitMode::Label(name.to_string())),
+                _ => Err("分割模式 label 需要标签列名，例如 label:provider".to_string()),
+            },
+            _ => Err(format!("无法识别的分割模式：{}", text)),
+        }
+    }
+}
+
+fn parse_prefix(text: &str) -> Result<u8, String> {
+    match text.trim().trim_start_matches('/').parse::<u8>() {
+        Ok(prefix) if prefix <= 32 => Ok(prefix),
+        _ => Err(format!("无效的前缀长度：{}（有效范围 0-32）", text)),
+    }
+}
+
+// 解析文件大小：100M、100MiB 按1024计算，100MB 按1000计算，不带单位为字节数
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number = number.parse::<u64>().ok()?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+// 解析超时时间：不带单位为秒，也可以使用 s、m、h、d 单位并组合，例如 30m、1d12h
+fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Some(seconds);
+    }
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        let multiplier = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        let value = std::mem::take(&mut number).parse::<u64>().ok()?;
+        total = total.checked_add(value.checked_mul(multiplier)?)?;
+    }
+    number.is_empty().then_some(total)
+}
+
+// 集合名、表名等标识符：字母开头，只包含字母、数字和下划线，不超过28个字符(iptables链名的上限)
+fn identifier(option: &str, value: String) -> Result<String, String> {
+    let mut chars = value.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && value.len() <= 28;
+    if valid {
+        Ok(value)
+    } else {
+        Err(format!("{} 只能包含字母、数字和下划线，以字母开头，不超过28个字符：{}", option, value))
+    }
+}
+
+// 命令行参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    #[serde(skip)]
+    pub help: bool,
+    #[serde(skip)]
+    pub command: Command,
+    #[serde(skip)]
+    pub set_operation: SetOperation,
+    pub cidrs: Vec<String>,
+    pub input: Option<String>,
+    pub input_format: Option<InputFormat>,
+    pub network_column: String,
+    pub with_labels: Vec<String>,
+    pub split: SplitMode,
+    pub output: String,
+    pub format: OutputFormat,
+    pub header: bool,
+    pub separator: String,
+    pub line_ending: String,
+    pub ports: Vec<u16>,
+    pub template: Option<String>,
+    pub output_dir: String,
+    pub file_name: Option<String>,
+    pub force: bool,
+    pub keep_previous: bool,
+    pub append: bool,
+    pub sorted: bool,
+    pub exclude: Vec<String>,
+    pub exclude_file: Option<String>,
+    pub exclude_bogons: bool,
+    pub target: Option<ExportTarget>,
+    pub set_name: String,
+    pub timeout: Option<u64>,
+    pub comment: Option<String>,
+    pub table: String,
+    pub action: String,
+    pub row_group_size: usize,
+    // 线程数只影响速度，不影响输出内容，所以不记入清单
+    #[serde(skip)]
+    pub threads: usize,
+    #[serde(skip)]
+    pub progress: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            help: false,
+            command: Command::Generate,
+            set_operation: SetOperation::Build,
+            cidrs: Vec::new(),
+            input: None,
+            input_format: None,
+            network_column: "cidr".to_string(),
+            with_labels: Vec::new(),
+            split: SplitMode::All,
+            output: "ip.txt".to_string(),
+            format: OutputFormat::Plain,
+            header: false,
+            separator: ",".to_string(),
+            line_ending: "\n".to_string(),
+            ports: Vec::new(),
+            template: None,
+            output_dir: String::new(),
+            file_name: None,
+            force: false,
+            keep_previous: false,
+            append: false,
+            sorted: false,
+            exclude: Vec::new(),
+            exclude_file: None,
+            exclude_bogons: false,
+            target: None,
+            set_name: "ip_list".to_string(),
+            timeout: None,
+            comment: None,
+            table: "filter".to_string(),
+            action: "DROP".to_string(),
+            row_group_size: columnar::DEFAULT_ROW_GROUP_SIZE,
+            threads: parallel::default_threads(),
+            progress: true,
+        }
+    }
+}
+
+// 取出选项的值，支持 --name value 和 --name=value 两种写法
+fn take_value(
+    name: &str,
+    inline: Option<String>,
+    args: &mut std::slice::Iter<'_, String>,
+) -> Result<String, String> {
+    match inline {
+        Some(value) => Ok(value),
+        None => args.next().cloned().ok_or_else(|| format!("选项 {} 缺少参数值", name)),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("verify") => {
+            options.command = Command::Verify;
+            iter.next();
+        }
+        Some("zones") => {
+            options.command = Command::Zones;
+            iter.next();
+        }
+        Some("export") => {
+            options.command = Command::Export;
+            iter.next();
+        }
+        Some("set") => {
+            options.command = Command::Set;
+            iter.next();
+            let name = iter.next().ok_or("set 需要指定操作：build、union、intersect、contains、cidrs、text")?;
+            options.set_operation =
+                SetOperation::from_name(name).ok_or_else(|| format!("无法识别的集合操作：{}", name))?;
+        }
+        _ => {}
+    }
+
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            options.cidrs.push(arg.clone());
+            continue;
+        }
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        match name.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-i" | "--input" => options.input = Some(take_value(&name, inline, &mut iter)?),
+            "--input-format" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.input_format = Some(
+                    InputFormat::from_name(&value)
+                        .ok_or_else(|| format!("无法识别的输入格式：{}", value))?,
+                );
+            }
+            "--exclude" => options.exclude.extend(split_list(&take_value(&name, inline, &mut iter)?)),
+            "--exclude-file" => options.exclude_file = Some(take_value(&name, inline, &mut iter)?),
+            "--exclude-bogons" => options.exclude_bogons = true,
+            "--network-column" => options.network_column = take_value(&name, inline, &mut iter)?,
+            "--with-labels" => {
+                options.with_labels = split_list(&take_value(&name, inline, &mut iter)?)
+            }
+            "-s" | "--split" => {
+                options.split = SplitMode::parse(&take_value(&name, inline, &mut iter)?)?
+            }
+            "-o" | "--output" => options.output = take_value(&name, inline, &mut iter)?,
+            "--stdout" => options.output = STDOUT.to_string(),
+            "-d" | "--output-dir" => options.output_dir = take_value(&name, inline, &mut iter)?,
+            "--file-name" => options.file_name = Some(take_value(&name, inline, &mut iter)?),
+            "-f" | "--format" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.format = OutputFormat::from_name(&value)
+                    .ok_or_else(|| format!("无法识别的输出格式：{}", value))?;
+            }
+            "--header" => options.header = true,
+            "--force" => options.force = true,
+            "--keep-previous" => options.keep_previous = true,
+            "--append" => options.append = true,
+            "--sorted" => options.sorted = true,
+            "--target" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.target = Some(
+                    ExportTarget::from_name(&value)
+                        .ok_or_else(|| format!("无法识别的导出格式：{}", value))?,
+                );
+            }
+            "--set-name" => options.set_name = identifier(&name, take_value(&name, inline, &mut iter)?)?,
+            "--table" => options.table = identifier(&name, take_value(&name, inline, &mut iter)?)?,
+            "--action" => options.action = identifier(&name, take_value(&name, inline, &mut iter)?)?,
+            "--timeout" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.timeout = Some(
+                    parse_duration(&value).ok_or_else(|| format!("无效的超时时间：{}", value))?,
+                );
+            }
+            "--comment" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                if value.contains(['"', '\n', '\r']) || value.chars().count() > 255 {
+                    return Err("注释不能包含双引号或换行，且不能超过255个字符".to_string());
+                }
+                options.comment = Some(value);
+            }
+            "--separator" => {
+                options.separator = output::unescape(&take_value(&name, inline, &mut iter)?);
+                if options.separator.is_empty() {
+                    return Err("--separator 不能为空".to_string());
+                }
+            }
+            "--line-ending" => {
+                options.line_ending = output::unescape(&take_value(&name, inline, &mut iter)?);
+                if options.line_ending.is_empty() {
+                    return Err("--line-ending 不能为空".to_string());
+                }
+            }
+            "--row-group-size" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.row_group_size = match value.trim().parse::<usize>() {
+                    Ok(0) | Err(_) => return Err(format!("行组的行数必须是大于0的数字：{}", value)),
+                    Ok(rows) => rows,
+                };
+            }
+            "--no-progress" => options.progress = false,
+            "--threads" => {
+                let value = take_value(&name, inline, &mut iter)?;
+                options.threads = match value.trim().parse::<usize>() {
+                    Ok(0) | Err(_) => return Err(format!("线程数必须是大于0的数字：{}", value)),
+                    Ok(threads) => threads,
+                };
+            }
+            "-p" | "--ports" => {
+                options.ports = ports::parse_ports(&take_value(&name, inline, &mut iter)?)?
+            }
+            "-t" | "--template" => options.template = Some(take_value(&name, inline, &mut iter)?),
+            _ => return Err(format!("无法识别的选项：{}", arg)),
+        }
+    }
+    if options.output == STDOUT && options.split != SplitMode::All {
+        return Err("写入标准输出时不能分割文件，请去掉 --split".to_string());
+    }
+    if options.output == STDOUT && options.append {
+        return Err("写入标准输出时不能使用 --append".to_string());
+    }
+    // 清单只记录输出目录中的文件名，-o 中带目录时下一次清理会删除输出目录中同名的其他文件
+    let output = options.output.as_str();
+    if options.command == Command::Generate
+        && output != STDOUT
+        && (output.is_empty() || output == "." || output == ".." || output.contains(['/', '\\']))
+    {
+        return Err(format!("-o 只能是文件名，不能包含路径，目录请用 --output-dir 指定：{}", output));
+    }
+    if options.command == Command::Export && options.target.is_none() {
+        return Err("export 需要用 --target 指定导出格式".to_string());
+    }
+    if options.sorted && !options.append {
+        return Err("--sorted 只能与 --append 一起使用".to_string());
+    }
+    if options.command == Command::Set {
+        check_set_arguments(&mut options)?;
+    }
+    let table_format = match options.format {
+        OutputFormat::Sqlite => Some("SQLite"),
+        OutputFormat::Parquet => Some("Parquet"),
+        _ => None,
+    };
+    if let Some(name) = table_format {
+        if options.output == STDOUT {
+            return Err(format!("{} 格式不能写入标准输出", name));
+        }
+        if options.template.is_some() {
+            return Err(format!("{} 格式不能与 --template 一起使用", name));
+        }
+        if matches!(options.split, SplitMode::MaxBytes(_)) {
+            return Err(format!("{} 格式无法按字节数分割，请使用 lines:<行数>", name));
+        }
+        // Parquet 的元数据在文件末尾，写完后无法在后面接着追加记录
+        if options.format == OutputFormat::Parquet && options.append {
+            return Err("Parquet 格式不能使用 --append".to_string());
+        }
+    }
+    // 没有用 -o 指定文件名时，扩展名与输出格式一致，例如 ip.jsonl、ip.db
+    if options.command == Command::Generate && options.output == "ip.txt" {
+        options.output = format!("ip.{}", options.format.extension());
+    }
+    Ok(options)
+}
+
+// set 各个操作需要的参数：union、intersect 至少一个集合文件，contains 为一个集合文件和要查询的地址
+fn check_set_arguments(options: &mut Options) -> Result<(), String> {
+    let files = options.cidrs.len();
+    match options.set_operation {
+        SetOperation::Build => {}
+        SetOperation::Union | SetOperation::Intersect if files == 0 => {
+            return Err("需要至少一个集合文件".to_string());
+        }
+        SetOperation::Contains if files < 2 => {
+            return Err("用法：set contains <集合文件> <IP地址...>".to_string());
+        }
+        SetOperation::Cidrs | SetOperation::Text if files != 1 => {
+            return Err("需要一个集合文件".to_string());
+        }
+        _ => {}
+    }
+    // 建立和合并集合时，没有用 -o 指定文件名就写入 ip.roaring
+    if options.output == "ip.txt" {
+        options.output = format!("ip.{}", bitmap::EXTENSION);
+    }
+    Ok(())
+}
+
+pub fn print_help() {
+    println!("用法：generate_all_ipv4_addresses [选项] [CIDR...]");
+    println!("      generate_all_ipv4_addresses verify [-d <目录>]");
+    println!("      generate_all_ipv4_addresses zones [-i <文件>] [CIDR...]");
+    println!("      generate_all_ipv4_addresses export --target <格式> [-i <文件>] [CIDR...]");
+    println!("      generate_all_ipv4_addresses set <操作> [参数...]");
+    println!();
+    println!("不带任何参数运行时进入交互模式。");
+    println!();
+    println!("输入：");
+    println!("  -i, --input <文件>           从文件读取CIDR");
+    println!("      --input-format <格式>     plain、csv、tsv、jsonl，默认按扩展名推断");
+    println!("      --network-column <列名>   CSV/TSV的列名(或从1开始的列号)、JSON的字段名，默认 cidr");
+    println!("      --exclude <CIDR,...>      排除这些地址，可以多次使用");
+    println!("      --exclude-file <文件>     从文件读取要排除的CIDR");
+    println!("      --exclude-bogons         排除保留地址、私有地址、文档地址和组播地址(bogon)");
+    println!();
+    println!("输出：");
+    println!("  -o, --output <文件>          全部写入时的文件名(不含目录)，默认 ip.<扩展名>，例如 ip.txt、ip.csv；为 - 时写入标准输出");
+    println!("      --stdout                 只把记录写入标准输出，提示信息写入标准错误，等同于 -o -");
+    println!("  -d, --output-dir <目录>      输出目录，不存在时自动创建，默认当前目录");
+    println!("      --file-name <模板>        分割写入的文件名，默认 ip_{{index}}.{{ext}}(按标签分割时 ip_{{label}}.{{ext}})");
+    println!("                               占位符：{{index}} {{padded}} {{total}} {{cidr}} {{prefix}} {{label}} {{label:列名}} {{ext}}");
+    println!("      --with-labels <列,...>    在IP地址后附加标签列，例如 provider");
+    println!("  -s, --split <模式>           equal:<份数>、lines:<行数>、label:<列名>、all(默认)");
+    println!("                               cidr：每个输入CIDR一个文件；prefix:<N>：每个 /N 网段一个文件");
+    println!("                               lines:<行数>/<N>：限制行数，但不把一个 /N 网段拆到两个文件");
+    println!("                               bytes:<大小>：限制每个文件的字节数(含表头)，例如 bytes:100MB");
+    println!("                               大小单位 K/M/G(或 KiB/MiB/GiB)按1024计算，KB/MB/GB 按1000计算");
+    println!("  -f, --format <格式>          plain(默认)、csv、jsonl、int、hex、binary、reverse、sqlite、parquet");
+    println!("                               reverse：反向解析名称，例如 4.3.2.1.in-addr.arpa.");
+    println!("                               sqlite：SQLite 数据库(默认 ip.db)，addresses 表中有整数地址 address、");
+    println!("                               文本地址 ip、端口 port(有端口时)、来源 cidr、文件序号 shard 和全部标签列，");
+    println!("                               address 上有索引");
+    println!("                               parquet：Parquet 文件(默认 ip.parquet，Snappy 压缩)，列为无符号32位整数 address、");
+    println!("                               port(有端口时)、cidr 和全部标签列；分割模式对应多个文件，不能追加");
+    println!("      --row-group-size <行数>   Parquet 每个行组的行数，默认 1048576");
+    println!("      --header                 在每个文件开头写入表头");
+    println!("      --separator <文本>        字段分隔符，默认逗号，支持 \\t 等转义");
+    println!("      --line-ending <文本>      记录分隔符，默认 \\n，例如 \\r\\n");
+    println!("  -p, --ports <端口,...>        每个IP地址与端口组合成 ip:port，例如 443,2053,8000-8010");
+    println!("  -t, --template <模板>        按模板输出每一行，例如 https://{{ip}}:{{port}}/cdn-cgi/trace");
+    println!("                               占位符：{{ip}} {{port}} {{int}} {{hex}} {{reverse}} {{cidr}} {{cidr_index}} {{index}}");
+    println!("                               以及 {{标签列名}}；{{{{ 和 }}}} 表示字面的大括号");
+    println!("      --threads <数量>          生成和格式化使用的线程数，默认为CPU核心数；输出内容与线程数无关");
+    println!("      --no-progress            不显示进度；默认在标准错误中显示生成和写入的进度、速度和预计剩余时间，");
+    println!("                               标准错误不是终端时改为每10秒输出一行进度");
+    println!("  -h, --help                   显示本帮助");
+    println!();
+    println!("清理：每次运行在输出目录写入 ip_manifest.json，记录生成的文件；");
+    println!("下一次运行只删除清单中列出的文件，删除前需要确认。");
+    println!("每个文件先写入同一目录下的 .<文件名>.partial，写完并同步到磁盘后才改名；");
+    println!("全部文件写完后清单才标记为完成(complete)。");
+    println!("      --force                  不经确认直接删除上次生成的文件");
+    println!("      --keep-previous          保留上次生成的文件");
+    println!();
+    println!("追加：按输出目录中的清单，以CIDR为单位计算哪些地址已经写过，只写入新的地址；");
+    println!("输入中重叠的地址也只写一次。影响每行内容的参数(格式、端口、模板等)必须与已有输出相同。");
+    println!("      --append                 全部写入时追加到文件末尾，分割写入时新增文件，序号接在已有文件之后");
+    println!("      --sorted                 与 --append 一起使用：已有地址和新地址合并后按地址排序重新写入");
+    println!();
+    println!("校验：verify 按输出目录中的清单检查每个文件的行数、大小和SHA-256，");
+    println!("并把全部记录与输入的CIDR对比，报告缺失、重复和超出范围的地址；不一致时返回非0。");
+    println!();
+    println!("反向区：zones 把覆盖输入所需的最少的反向区名称写入标准输出，每行一个；");
+    println!("IPv4按 /8、/16、/24 对齐，前缀长于 /24 时使用 RFC 2317 的无类别委派名称(例如 0/26.2.0.192.in-addr.arpa.)；");
+    println!("IPv6按4位对齐，写成 ip6.arpa. 名称。");
+    println!();
+    println!("导出：export 先合并重叠和相邻的CIDR，再按 --target 写入标准输出(有多个文件时写入输出目录)：");
+    println!("      --target <格式>           ipset、nftables、iptables、mikrotik，以及扫描器 masscan、nmap、zmap");
+    println!("                               防火墙格式只包含排除之后的网络；masscan 写成 exclude = 行，");
+    println!("                               nmap、zmap 把目标和排除地址分别写入输出目录(-d)中的两个文件：");
+    println!("                               nmap_targets.txt/nmap_exclude.txt、zmap_whitelist.txt/zmap_blacklist.txt");
+    println!("                               nginx-realip(set_real_ip_from)、nginx-geo、nginx-allow、nginx-deny，");
+    println!("                               haproxy(ACL文件，每行一个CIDR)；输出按地址排序，便于对比不同版本");
+    println!("      --set-name <名称>         ipset/nftables 的集合名、iptables 的链名、MikroTik 的列表名、");
+    println!("                               nginx geo 的变量名、HAProxy 的 ACL 名，默认 ip_list");
+    println!("      --timeout <时间>          条目的超时时间，例如 3600、30m、1d(iptables 不支持)");
+    println!("      --comment <文本>          每个条目的注释(nginx、HAProxy 写在开头的注释行)");
+    println!("      --table <名称>            nftables 的表名(inet 族)，默认 filter");
+    println!("      --action <目标>           iptables 规则的目标，默认 DROP");
+    println!("                               iptables 输出为完整的 *filter ... COMMIT 块，请用 iptables-restore --noflush 加载，");
+    println!("                               不加 --noflush 会清空整个 filter 表");
+    println!();
+    println!("地址集合：set 把地址集合保存为 roaring 位图文件，几百万个地址通常只需要几KB：");
+    println!("      set build [-i <文件>] [CIDR...] [-o <文件>]     由输入建立集合，会去掉 --exclude 等排除的地址");
+    println!("      set union <集合文件...> [-o <文件>]             并集");
+    println!("      set intersect <集合文件...> [-o <文件>]         交集");
+    println!("      set contains <集合文件> <IP地址...>             逐个输出是否在集合中，有地址不在集合中时返回1");
+    println!("      set cidrs <集合文件>                           转换回最少的CIDR，写入标准输出");
+    println!("      set text <集合文件>                            逐个列出集合中的地址，写入标准输出");
+    println!("      集合文件默认写入 ip.roaring，-o - 写入标准输出");
+}
@@ -0,0 +1,166 @@
+use crate::generate::Record;
+use crate::output::{RecordFormat, RecordStats};
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::column::reader::get_typed_column_reader;
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+
+// 默认每个行组的行数，与常见的 Parquet 写入工具一致
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 1024 * 1024;
+
+fn parquet_error(err: parquet::errors::ParquetError) -> io::Error {
+    io::Error::other(format!("Parquet 出错：{}", err))
+}
+
+fn column(name: &str, physical: PhysicalType, logical: LogicalType) -> io::Result<Arc<Type>> {
+    let column = Type::primitive_type_builder(name, physical)
+        .with_repetition(Repetition::REQUIRED)
+        .with_logical_type(Some(logical))
+        .build()
+        .map_err(parquet_error)?;
+    Ok(Arc::new(column))
+}
+
+// 标签列不能与固定的列重名，在创建任何文件之前调用
+pub fn check_columns(format: &RecordFormat) -> io::Result<()> {
+    for name in &format.input().label_names {
+        if ["address", "port", "cidr"].contains(&name.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("标签列 {} 与 Parquet 文件中的列名重复", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// 列：address(无符号32位整数)、port(有端口时，无符号16位整数)、cidr，以及输入中的全部标签列
+fn schema(format: &RecordFormat) -> io::Result<Arc<Type>> {
+    check_columns(format)?;
+    let unsigned = |bits| LogicalType::Integer { bit_width: bits, is_signed: false };
+    let mut fields = vec![column("address", PhysicalType::INT32, unsigned(32))?];
+    if format.ports {
+        fields.push(column("port", PhysicalType::INT32, unsigned(16))?);
+    }
+    fields.push(column("cidr", PhysicalType::BYTE_ARRAY, LogicalType::String)?);
+    for name in &format.input().label_names {
+        fields.push(column(name, PhysicalType::BYTE_ARRAY, LogicalType::String)?);
+    }
+    let schema = Type::group_type_builder("addresses")
+        .with_fields(fields)
+        .build()
+        .map_err(parquet_error)?;
+    Ok(Arc::new(schema))
+}
+
+// 一个行组的数据，按列存放
+#[derive(Default)]
+struct RowGroup {
+    addresses: Vec<i32>,
+    ports: Vec<i32>,
+    cidrs: Vec<ByteArray>,
+    labels: Vec<Vec<ByteArray>>,
+}
+
+impl RowGroup {
+    fn write(&mut self, writer: &mut SerializedFileWriter<File>, ports: bool) -> io::Result<()> {
+        let mut group = writer.next_row_group().map_err(parquet_error)?;
+        let mut strings = vec![std::mem::take(&mut self.cidrs)];
+        strings.extend(self.labels.iter_mut().map(std::mem::take));
+        let mut integers = vec![std::mem::take(&mut self.addresses)];
+        if ports {
+            integers.push(std::mem::take(&mut self.ports));
+        }
+        // 按 schema 中的顺序：先是整数列，然后是 cidr 和标签列
+        for values in &integers {
+            let mut column = group.next_column().map_err(parquet_error)?.expect("schema 中有这一列");
+            column.typed::<Int32Type>().write_batch(values, None, None).map_err(parquet_error)?;
+            column.close().map_err(parquet_error)?;
+        }
+        for values in &strings {
+            let mut column = group.next_column().map_err(parquet_error)?.expect("schema 中有这一列");
+            column.typed::<ByteArrayType>().write_batch(values, None, None).map_err(parquet_error)?;
+            column.close().map_err(parquet_error)?;
+        }
+        group.close().map_err(parquet_error)?;
+        Ok(())
+    }
+}
+
+// 把记录写成 path 中的 Parquet 文件，每 row_group_size 条记录一个行组，使用 Snappy 压缩
+pub fn write_parquet<I: Iterator<Item = Record>>(
+    path: &Path,
+    records: I,
+    format: &RecordFormat,
+    row_group_size: usize,
+) -> io::Result<RecordStats> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_created_by(format!("generate_all_ipv4_addresses {}", env!("CARGO_PKG_VERSION")))
+        .build();
+    let file = File::create(path)?;
+    let mut writer =
+        SerializedFileWriter::new(file, schema(format)?, Arc::new(properties)).map_err(parquet_error)?;
+
+    let input = format.input();
+    let label_count = input.label_names.len();
+    let mut group = RowGroup { labels: vec![Vec::new(); label_count], ..RowGroup::default() };
+    let mut stats = RecordStats::default();
+    for record in records {
+        let source = &input.records[record.source];
+        group.addresses.push(record.ip as i32);
+        if let Some(port) = record.port {
+            group.ports.push(port as i32);
+        }
+        group.cidrs.push(ByteArray::from(source.network.to_string().as_str()));
+        for (label, values) in group.labels.iter_mut().enumerate() {
+            values.push(ByteArray::from(input.label(source, label)));
+        }
+        stats.add(&record);
+        if group.addresses.len() >= row_group_size {
+            group.write(&mut writer, format.ports)?;
+        }
+    }
+    if !group.addresses.is_empty() {
+        group.write(&mut writer, format.ports)?;
+    }
+    writer.close().map_err(parquet_error)?;
+    Ok(stats)
+}
+
+// 按写入顺序读出文件中的全部地址和端口，校验时使用
+pub fn read_addresses(path: &Path, ports: bool) -> io::Result<Vec<(u32, Option<u16>)>> {
+    let reader = SerializedFileReader::new(File::open(path)?).map_err(parquet_error)?;
+    let mut records = Vec::new();
+    for index in 0..reader.num_row_groups() {
+        let group = reader.get_row_group(index).map_err(parquet_error)?;
+        let rows = group.metadata().num_rows() as usize;
+        let mut columns = Vec::new();
+        for column in 0..if ports { 2 } else { 1 } {
+            let mut values = Vec::with_capacity(rows);
+            let column = group.get_column_reader(column).map_err(parquet_error)?;
+            let mut column = get_typed_column_reader::<Int32Type>(column);
+            while values.len() < rows {
+                let (read, _, _) =
+                    column.read_records(rows - values.len(), None, None, &mut values).map_err(parquet_error)?;
+                if read == 0 {
+                    break;
+                }
+            }
+            columns.push(values);
+        }
+        for (row, &address) in columns[0].iter().enumerate() {
+            let port = columns.get(1).and_then(|ports| ports.get(row)).map(|&port| port as u16);
+            records.push((address as u32, port));
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,74 @@
+// 0-255 的十进制文本，最后一个字节为长度
+// 下面的函数都直接写入调用方的缓冲区，不分配内存，缓冲区可以在记录之间重复使用
+const fn octet_table() -> [[u8; 4]; 256] {
+    let mut table = [[0u8; 4]; 256];
+    let mut n = 0;
+    while n < 256 {
+        let (hundreds, tens, ones) = ((n / 100) as u8, (n / 10 % 10) as u8, (n % 10) as u8);
+        table[n] = if n >= 100 {
+            [b'0' + hundreds, b'0' + tens, b'0' + ones, 3]
+        } else if n >= 10 {
+            [b'0' + tens, b'0' + ones, 0, 2]
+        } else {
+            [b'0' + ones, 0, 0, 1]
+        };
+        n += 1;
+    }
+    table
+}
+
+static OCTETS: [[u8; 4]; 256] = octet_table();
+
+// 追加一个字节的十进制文本，例如 192
+#[inline]
+pub fn push_octet(out: &mut Vec<u8>, octet: u8) {
+    let entry = &OCTETS[octet as usize];
+    out.extend_from_slice(&entry[..entry[3] as usize]);
+}
+
+// 追加点分十进制的IPv4地址，例如 192.0.2.1
+#[inline]
+pub fn push_ipv4(out: &mut Vec<u8>, ip: u32) {
+    let [a, b, c, d] = ip.to_be_bytes();
+    // 最长15个字节，先在栈上拼好再一次写入
+    let mut text = [0u8; 15];
+    let mut len = 0;
+    for (index, octet) in [a, b, c, d].into_iter().enumerate() {
+        if index > 0 {
+            text[len] = b'.';
+            len += 1;
+        }
+        let entry = &OCTETS[octet as usize];
+        let size = entry[3] as usize;
+        text[len..len + size].copy_from_slice(&entry[..size]);
+        len += size;
+    }
+    out.extend_from_slice(&text[..len]);
+}
+
+// 追加无符号整数的十进制文本，用于整数地址、端口和序号
+#[inline]
+pub fn push_decimal(out: &mut Vec<u8>, mut value: u64) {
+    let mut text = [0u8; 20];
+    let mut start = text.len();
+    loop {
+        start -= 1;
+        text[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(&text[start..]);
+}
+
+// 追加8位小写十六进制，例如 c0000201
+#[inline]
+pub fn push_hex(out: &mut Vec<u8>, value: u32) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut text = [0u8; 8];
+    for (index, digit) in text.iter_mut().enumerate() {
+        *digit = DIGITS[(value >> (28 - index * 4)) as usize & 0xf];
+    }
+    out.extend_from_slice(&text);
+}
@@ -0,0 +1,49 @@
+use crate::cli::Options;
+use crate::input::{self, InputFormat};
+use crate::ranges::{self, Range4};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use std::io;
+
+
+// 不应出现在公网上的IPv4地址(bogon)：保留地址、私有地址、文档地址、组播等，参考 RFC 6890
+pub const BOGONS: &[&str] = &[
+    "0.0.0.0/8",       // 本网络
+    "10.0.0.0/8",      // 私有地址
+    "100.64.0.0/10",   // 运营商级NAT
+    "127.0.0.0/8",     // 环回地址
+    "169.254.0.0/16",  // 链路本地地址
+    "172.16.0.0/12",   // 私有地址
+    "192.0.0.0/24",    // IETF协议分配
+    "192.0.2.0/24",    // 文档地址 TEST-NET-1
+    "192.168.0.0/16",  // 私有地址
+    "198.18.0.0/15",   // 网络性能测试
+    "198.51.100.0/24", // 文档地址 TEST-NET-2
+    "203.0.113.0/24",  // 文档地址 TEST-NET-3
+    "224.0.0.0/4",     // 组播
+    "240.0.0.0/4",     // 保留地址，包括 255.255.255.255
+];
+
+// 根据 --exclude、--exclude-file 和 --exclude-bogons 得到要排除的区间，已排序、已合并
+// 生成、追加、导出都使用同一组排除区间
+pub fn load_exclusions(options: &Options) -> io::Result<Vec<Range4>> {
+    let mut records = input::from_cidrs(&options.exclude)?.records;
+    if let Some(filename) = &options.exclude_file {
+        let format = InputFormat::from_path(filename);
+        records.extend(input::read_input(filename, format, &options.network_column)?.records);
+    }
+
+    let mut excluded = Vec::new();
+    for record in records {
+        match record.network {
+            IpNetwork::V4(network) => excluded.push(ranges::network_range(network)),
+            IpNetwork::V6(_) => eprintln!("IPv6 CIDR范围不受支持。"),
+        }
+    }
+    if options.exclude_bogons {
+        for bogon in BOGONS {
+            let network: Ipv4Network = bogon.parse().expect("内置的bogon列表格式正确");
+            excluded.push(ranges::network_range(network));
+        }
+    }
+    Ok(ranges::merge(excluded))
+}
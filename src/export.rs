@@ -0,0 +1,276 @@
+use crate::cli::Options;
+use crate::ports;
+use crate::ranges::{self, Range4};
+use ipnetwork::Ipv4Network;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+
+// 导出给防火墙等外部工具的格式，内容是合并后的CIDR，而不是逐个IP地址
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportTarget {
+    Ipset,       // ipset restore 脚本
+    Nftables,    // nftables 的 set 定义，区间元素
+    Iptables,    // iptables-restore 规则
+    Mikrotik,    // MikroTik /ip firewall address-list 脚本
+    Masscan,     // masscan 配置文件，range = 和 ports =
+    Nmap,        // nmap -iL 的目标文件和 --excludefile 的排除文件
+    Zmap,        // zmap 的白名单和黑名单文件
+    NginxRealIp, // nginx set_real_ip_from 指令
+    NginxGeo,    // nginx geo 块，匹配的地址取值为1
+    NginxAllow,  // nginx allow 列表，最后 deny all
+    NginxDeny,   // nginx deny 列表
+    Haproxy,     // HAProxy 的 ACL 文件，用 acl <名称> src -f <文件> 加载
+}
+
+impl ExportTarget {
+    pub fn from_name(name: &str) -> Option<ExportTarget> {
+        match name.to_ascii_lowercase().as_str() {
+            "ipset" => Some(ExportTarget::Ipset),
+            "nft" | "nftables" => Some(ExportTarget::Nftables),
+            "iptables" | "iptables-restore" => Some(ExportTarget::Iptables),
+            "mikrotik" | "routeros" => Some(ExportTarget::Mikrotik),
+            "masscan" => Some(ExportTarget::Masscan),
+            "nmap" => Some(ExportTarget::Nmap),
+            "zmap" => Some(ExportTarget::Zmap),
+            "nginx-realip" | "nginx-real-ip" => Some(ExportTarget::NginxRealIp),
+            "nginx-geo" => Some(ExportTarget::NginxGeo),
+            "nginx-allow" => Some(ExportTarget::NginxAllow),
+            "nginx-deny" => Some(ExportTarget::NginxDeny),
+            "haproxy" | "haproxy-acl" => Some(ExportTarget::Haproxy),
+            _ => None,
+        }
+    }
+}
+
+// 导出使用的网络，都已合并成最少的CIDR并按地址排序
+pub struct ExportSet {
+    pub targets: Vec<Ipv4Network>,  // 输入的网络
+    pub excluded: Vec<Ipv4Network>, // 要排除的地址，只保留落在 targets 中的部分
+    pub effective: Vec<Ipv4Network>, // targets 去掉 excluded 之后的网络，与生成的地址一致
+}
+
+impl ExportSet {
+    pub fn new(targets: &[Range4], excluded: &[Range4]) -> ExportSet {
+        ExportSet {
+            targets: ranges::to_networks(targets),
+            excluded: ranges::to_networks(&ranges::intersection(targets, excluded)),
+            effective: ranges::to_networks(&ranges::difference(targets, excluded)),
+        }
+    }
+}
+
+// 导出得到的一个文件
+pub struct ExportFile {
+    pub name: &'static str,
+    pub content: Vec<u8>,
+}
+
+// RouterOS 的时间写法，例如 1d02:00:00
+fn routeros_time(seconds: u64) -> String {
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+    let time = format!("{:02}:{:02}:{:02}", rest / 3600, rest % 3600 / 60, rest % 60);
+    if days > 0 {
+        format!("{}d{}", days, time)
+    } else {
+        time
+    }
+}
+
+fn write_ipset<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    let name = &options.set_name;
+    let maxelem = networks.len().max(65536);
+    write!(out, "create {} hash:net family inet hashsize 1024 maxelem {}", name, maxelem)?;
+    if let Some(timeout) = options.timeout {
+        write!(out, " timeout {}", timeout)?;
+    }
+    if options.comment.is_some() {
+        write!(out, " comment")?;
+    }
+    writeln!(out, " -exist")?;
+    for network in networks {
+        write!(out, "add {} {}", name, network)?;
+        if let Some(comment) = &options.comment {
+            write!(out, " comment \"{}\"", comment)?;
+        }
+        writeln!(out, " -exist")?;
+    }
+    Ok(())
+}
+
+fn write_nftables<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    writeln!(out, "table inet {} {{", options.table)?;
+    writeln!(out, "\tset {} {{", options.set_name)?;
+    writeln!(out, "\t\ttype ipv4_addr")?;
+    match options.timeout {
+        Some(timeout) => {
+            writeln!(out, "\t\tflags interval, timeout")?;
+            writeln!(out, "\t\ttimeout {}s", timeout)?;
+        }
+        None => writeln!(out, "\t\tflags interval")?,
+    }
+    if let Some(comment) = &options.comment {
+        writeln!(out, "\t\tcomment \"{}\"", comment)?;
+    }
+    if !networks.is_empty() {
+        writeln!(out, "\t\telements = {{")?;
+        for (index, network) in networks.iter().enumerate() {
+            let comma = if index + 1 < networks.len() { "," } else { "" };
+            writeln!(out, "\t\t\t{}{}", network, comma)?;
+        }
+        writeln!(out, "\t\t}}")?;
+    }
+    writeln!(out, "\t}}")?;
+    writeln!(out, "}}")
+}
+
+// iptables 没有超时，只生成一条自定义链，需要时在 INPUT 等链中跳转过去
+fn write_iptables<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    let chain = &options.set_name;
+    writeln!(out, "# 在需要的链中跳转到这条链，例如：-A INPUT -j {}", chain)?;
+    writeln!(out, "# 请用 iptables-restore --noflush 加载；不加 --noflush 会先清空整个 filter 表中的规则")?;
+    writeln!(out, "*filter")?;
+    writeln!(out, ":{} - [0:0]", chain)?;
+    for network in networks {
+        write!(out, "-A {} -s {}", chain, network)?;
+        if let Some(comment) = &options.comment {
+            write!(out, " -m comment --comment \"{}\"", comment)?;
+        }
+        writeln!(out, " -j {}", options.action)?;
+    }
+    writeln!(out, "COMMIT")
+}
+
+fn write_mikrotik<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    writeln!(out, "/ip firewall address-list")?;
+    for network in networks {
+        write!(out, "add list={} address={}", options.set_name, network)?;
+        if let Some(comment) = &options.comment {
+            write!(out, " comment=\"{}\"", comment)?;
+        }
+        if let Some(timeout) = options.timeout {
+            write!(out, " timeout={}", routeros_time(timeout))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// nginx 和 HAProxy 的配置没有逐条注释，--comment 写在开头的注释行中
+fn write_comment<W: Write>(out: &mut W, options: &Options) -> io::Result<()> {
+    match &options.comment {
+        Some(comment) => writeln!(out, "# {}", comment),
+        None => Ok(()),
+    }
+}
+
+// 在 http 或 server 块中 include，real_ip_header 按前面的代理设置
+fn write_nginx_real_ip<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    write_comment(out, options)?;
+    for network in networks {
+        writeln!(out, "set_real_ip_from {};", network)?;
+    }
+    writeln!(out, "# real_ip_header X-Forwarded-For;")
+}
+
+// geo 块放在 http 块中，之后用 if (${名称}) 判断
+fn write_nginx_geo<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    write_comment(out, options)?;
+    writeln!(out, "geo ${} {{", options.set_name)?;
+    writeln!(out, "    default 0;")?;
+    for network in networks {
+        writeln!(out, "    {} 1;", network)?;
+    }
+    writeln!(out, "}}")
+}
+
+// nginx 按顺序匹配 allow/deny，允许列表的最后拒绝其它地址
+fn write_nginx_access<W: Write>(
+    out: &mut W,
+    networks: &[Ipv4Network],
+    options: &Options,
+    allow: bool,
+) -> io::Result<()> {
+    write_comment(out, options)?;
+    let directive = if allow { "allow" } else { "deny" };
+    for network in networks {
+        writeln!(out, "{} {};", directive, network)?;
+    }
+    if allow {
+        writeln!(out, "deny all;")?;
+    }
+    Ok(())
+}
+
+fn write_haproxy<W: Write>(out: &mut W, networks: &[Ipv4Network], options: &Options) -> io::Result<()> {
+    writeln!(out, "# acl {} src -f <本文件>", options.set_name)?;
+    write_comment(out, options)?;
+    write_list(out, networks)
+}
+
+// masscan 自己处理排除的地址，端口必须指定
+fn write_masscan<W: Write>(out: &mut W, set: &ExportSet, options: &Options) -> io::Result<()> {
+    if options.ports.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "masscan 配置需要用 --ports 指定端口"));
+    }
+    writeln!(out, "# masscan -c <本文件>")?;
+    writeln!(out, "ports = {}", ports::format_ports(&options.ports))?;
+    for network in &set.targets {
+        writeln!(out, "range = {}", network)?;
+    }
+    for network in &set.excluded {
+        writeln!(out, "exclude = {}", network)?;
+    }
+    Ok(())
+}
+
+// 每行一个CIDR，nmap 和 zmap 的目标文件、排除文件都是这种格式
+fn write_list<W: Write>(out: &mut W, networks: &[Ipv4Network]) -> io::Result<()> {
+    for network in networks {
+        writeln!(out, "{}", network)?;
+    }
+    Ok(())
+}
+
+fn single<F>(name: &'static str, write: F) -> io::Result<Vec<ExportFile>>
+where
+    F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+{
+    let mut content = Vec::new();
+    write(&mut content)?;
+    Ok(vec![ExportFile { name, content }])
+}
+
+fn pair(
+    names: (&'static str, &'static str),
+    set: &ExportSet,
+) -> io::Result<Vec<ExportFile>> {
+    let mut targets = Vec::new();
+    write_list(&mut targets, &set.targets)?;
+    let mut excluded = Vec::new();
+    write_list(&mut excluded, &set.excluded)?;
+    Ok(vec![
+        ExportFile { name: names.0, content: targets },
+        ExportFile { name: names.1, content: excluded },
+    ])
+}
+
+// 按 target 导出；防火墙、nginx 和 HAProxy 只能使用排除之后的网络，扫描器的排除地址单独写出
+pub fn export(target: ExportTarget, set: &ExportSet, options: &Options) -> io::Result<Vec<ExportFile>> {
+    let networks = &set.effective;
+    match target {
+        ExportTarget::Ipset => single("ipset.txt", |out| write_ipset(out, networks, options)),
+        ExportTarget::Nftables => single("nftables.nft", |out| write_nftables(out, networks, options)),
+        ExportTarget::Iptables => single("iptables.rules", |out| write_iptables(out, networks, options)),
+        ExportTarget::Mikrotik => single("mikrotik.rsc", |out| write_mikrotik(out, networks, options)),
+        ExportTarget::NginxRealIp => single("nginx_real_ip.conf", |out| write_nginx_real_ip(out, networks, options)),
+        ExportTarget::NginxGeo => single("nginx_geo.conf", |out| write_nginx_geo(out, networks, options)),
+        ExportTarget::NginxAllow => single("nginx_allow.conf", |out| write_nginx_access(out, networks, options, true)),
+        ExportTarget::NginxDeny => single("nginx_deny.conf", |out| write_nginx_access(out, networks, options, false)),
+        ExportTarget::Haproxy => single("haproxy.acl", |out| write_haproxy(out, networks, options)),
+        ExportTarget::Masscan => single("masscan.conf", |out| write_masscan(out, set, options)),
+        ExportTarget::Nmap => pair(("nmap_targets.txt", "nmap_exclude.txt"), set),
+        ExportTarget::Zmap => pair(("zmap_whitelist.txt", "zmap_blacklist.txt"), set),
+    }
+}
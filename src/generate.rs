@@ -0,0 +1,179 @@
+use crate::input::InputSet;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::ranges::{self, Range4};
+use ipnetwork::IpNetwork;
+use std::io;
+
+
+// 生成的IP地址(u32)，source 为它所属的输入记录的下标
+#[derive(Clone, Copy, Debug)]
+pub struct GeneratedIp {
+    pub ip: u32,
+    pub source: usize,
+}
+
+// 一条输出记录：IP地址，加上可选的端口，index 为记录在全部输出中的序号(从0开始)
+#[derive(Clone, Copy, Debug)]
+pub struct Record {
+    pub ip: u32,
+    pub source: usize,
+    pub port: Option<u16>,
+    pub index: usize,
+}
+
+// IP地址与端口的笛卡尔积，按需计算每条记录，不会把组合结果全部放进内存
+// 记录顺序：先按IP地址，同一个IP地址再按端口列表的顺序
+#[derive(Clone, Copy)]
+pub struct RecordSet<'a> {
+    ips: &'a [GeneratedIp],
+    ports: &'a [u16],
+}
+
+impl<'a> RecordSet<'a> {
+    pub fn new(ips: &'a [GeneratedIp], ports: &'a [u16]) -> RecordSet<'a> {
+        RecordSet { ips, ports }
+    }
+
+    // 每个IP地址对应的记录数
+    pub fn per_ip(&self) -> usize {
+        self.ports.len().max(1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ips.len() * self.per_ip()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ips.is_empty()
+    }
+
+    pub fn ips(&self) -> &'a [GeneratedIp] {
+        self.ips
+    }
+
+    pub fn get(&self, index: usize) -> Record {
+        let generated = self.ips[index / self.per_ip()];
+        Record {
+            ip: generated.ip,
+            source: generated.source,
+            port: self.ports.get(index % self.per_ip()).copied(),
+            index,
+        }
+    }
+
+    // 第 start 条到第 end 条(不含)记录
+    pub fn range(&self, start: usize, end: usize) -> impl Iterator<Item = Record> + 'a {
+        let set = *self;
+        (start..end).map(move |index| set.get(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Record> + 'a {
+        self.range(0, self.len())
+    }
+}
+
+// 要生成的一段连续地址，以及它们所属的输入记录
+#[derive(Clone, Copy)]
+struct Span {
+    start: u32,
+    end: u32,
+    source: usize,
+}
+
+impl Span {
+    fn len(&self) -> usize {
+        (self.end - self.start) as usize + 1
+    }
+}
+
+// 按顺序展开所有地址段；结果先按总数分配好，再切成固定大小的块由多个线程分别填写，
+// 每个块在结果中的位置是固定的，所以与单线程生成的顺序完全相同
+fn expand(spans: &[Span], threads: usize, progress: Option<&Progress>) -> Vec<GeneratedIp> {
+    // offsets[i] 为第 i 段的第一个地址在结果中的位置
+    let mut offsets = Vec::with_capacity(spans.len());
+    let mut total = 0;
+    for span in spans {
+        offsets.push(total);
+        total += span.len();
+    }
+    if let Some(progress) = progress {
+        progress.start_generate(total as u64);
+    }
+    let mut ips = vec![GeneratedIp { ip: 0, source: 0 }; total];
+    let chunks: Vec<(usize, &mut [GeneratedIp])> = ips
+        .chunks_mut(parallel::CHUNK_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| (index * parallel::CHUNK_SIZE, chunk))
+        .collect();
+    parallel::for_each(chunks, threads, |(first, chunk)| {
+        let mut index = offsets.partition_point(|&offset| offset <= first) - 1;
+        let mut ip = spans[index].start + (first - offsets[index]) as u32;
+        for slot in chunk.iter_mut() {
+            *slot = GeneratedIp { ip, source: spans[index].source };
+            if ip == spans[index].end {
+                index += 1;
+                ip = spans.get(index).map_or(0, |span| span.start);
+            } else {
+                ip += 1;
+            }
+        }
+        if let Some(progress) = progress {
+            progress.add_generated(chunk.len() as u64);
+        }
+    });
+    ips
+}
+
+// 生成IP地址列表，跳过 excluded(已排序、已合并)中的地址；threads 为使用的线程数，
+// 有 progress 时在生成过程中更新进度
+pub fn generate_ips(
+    input: &InputSet,
+    excluded: &[Range4],
+    threads: usize,
+    progress: Option<&Progress>,
+) -> io::Result<Vec<GeneratedIp>> {
+    let mut spans = Vec::new();
+
+    for (source, record) in input.records.iter().enumerate() {
+        match record.network {
+            IpNetwork::V4(v4_network) => {
+                for (start, end) in ranges::subtract(ranges::network_range(v4_network), excluded) {
+                    spans.push(Span { start, end, source });
+                }
+            }
+            IpNetwork::V6(_) => {
+                eprintln!("IPv6 CIDR范围不受支持。");
+            }
+        }
+    }
+    Ok(expand(&spans, threads, progress))
+}
+
+// 只生成 covered(已排序、已合并)之外的IP地址，用于追加到已有输出；
+// 输入中互相重叠的部分也只生成一次，顺序与 generate_ips 相同
+pub fn generate_unique_ips(
+    input: &InputSet,
+    covered: &[Range4],
+    threads: usize,
+    progress: Option<&Progress>,
+) -> io::Result<Vec<GeneratedIp>> {
+    let mut covered = covered.to_vec();
+    let mut spans = Vec::new();
+
+    for (source, record) in input.records.iter().enumerate() {
+        match record.network {
+            IpNetwork::V4(v4_network) => {
+                let range = ranges::network_range(v4_network);
+                for (start, end) in ranges::subtract(range, &covered) {
+                    spans.push(Span { start, end, source });
+                }
+                ranges::insert(&mut covered, range);
+            }
+            IpNetwork::V6(_) => {
+                eprintln!("IPv6 CIDR范围不受支持。");
+            }
+        }
+    }
+    Ok(expand(&spans, threads, progress))
+}
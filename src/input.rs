@@ -0,0 +1,302 @@
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+
+// 输入文件的格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    Plain,     // 每行一个CIDR
+    Csv,       // 逗号分隔，第一行为表头
+    Tsv,       // 制表符分隔，第一行为表头
+    JsonLines, // 每行一个JSON对象
+}
+
+impl InputFormat {
+    pub fn from_name(name: &str) -> Option<InputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "plain" | "txt" => Some(InputFormat::Plain),
+            "csv" => Some(InputFormat::Csv),
+            "tsv" => Some(InputFormat::Tsv),
+            "jsonl" | "ndjson" | "json" => Some(InputFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    // 根据文件扩展名推断格式，无法识别时按纯文本处理
+    pub fn from_path(path: &str) -> InputFormat {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        match InputFormat::from_name(extension) {
+            Some(format) => format,
+            None => InputFormat::Plain,
+        }
+    }
+}
+
+// 一条输入记录：网络地址，以及同一行其余列的值(标签)
+#[derive(Clone, Debug)]
+pub struct InputRecord {
+    pub network: IpNetwork,
+    pub labels: Vec<String>,
+}
+
+// 解析后的全部输入，labels 与 label_names 按下标一一对应
+#[derive(Clone, Debug, Default)]
+pub struct InputSet {
+    pub label_names: Vec<String>,
+    pub records: Vec<InputRecord>,
+}
+
+impl InputSet {
+    // 按列名查找标签的下标
+    pub fn label_index(&self, name: &str) -> Option<usize> {
+        self.label_names.iter().position(|label| label == name)
+    }
+
+    // 取某条记录的标签值，缺失时返回空字符串
+    pub fn label<'a>(&'a self, record: &'a InputRecord, index: usize) -> &'a str {
+        record.labels.get(index).map(|s| s.as_str()).unwrap_or("")
+    }
+}
+
+fn parse_network(text: &str, line_number: usize) -> io::Result<IpNetwork> {
+    text.parse::<IpNetwork>().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("第{}行：解析{}时出现 {} 错误！", line_number, text, e),
+        )
+    })
+}
+
+// 把命令行或键盘输入的CIDR列表转换成没有标签的输入
+pub fn from_cidrs(cidrs: &[String]) -> io::Result<InputSet> {
+    let mut records = Vec::new();
+    for cidr in cidrs {
+        let network = cidr.parse::<IpNetwork>().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("解析{}时出现 {} 错误！", cidr, e),
+            )
+        })?;
+        records.push(InputRecord { network, labels: Vec::new() });
+    }
+    Ok(InputSet { label_names: Vec::new(), records })
+}
+
+// 从外部文件中读取输入，column 为网络地址所在的列名(CSV/TSV)或字段名(JSON lines)
+pub fn read_input(filename: &str, format: InputFormat, column: &str) -> io::Result<InputSet> {
+    let file = File::open(Path::new(filename))?;
+    let lines = BufReader::new(file).lines();
+    match format {
+        InputFormat::Plain => read_plain(lines),
+        InputFormat::Csv => read_delimited(lines, ',', column),
+        InputFormat::Tsv => read_delimited(lines, '\t', column),
+        InputFormat::JsonLines => read_json_lines(lines, column),
+    }
+}
+
+fn read_plain<I: Iterator<Item = io::Result<String>>>(lines: I) -> io::Result<InputSet> {
+    let mut records = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let network = parse_network(line, number + 1)?;
+        records.push(InputRecord { network, labels: Vec::new() });
+    }
+    Ok(InputSet { label_names: Vec::new(), records })
+}
+
+// 拆分一行CSV/TSV，支持双引号包裹的字段和 "" 转义
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(field.trim().to_string());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+// 查找网络地址所在的列：先按列名匹配，再按从1开始的列号匹配
+fn find_column(header: &[String], column: &str) -> io::Result<usize> {
+    if let Some(index) = header.iter().position(|name| name.eq_ignore_ascii_case(column)) {
+        return Ok(index);
+    }
+    match column.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= header.len() => Ok(number - 1),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("表头中找不到网络地址列：{}（表头：{}）", column, header.join(",")),
+        )),
+    }
+}
+
+fn read_delimited<I: Iterator<Item = io::Result<String>>>(
+    lines: I,
+    delimiter: char,
+    column: &str,
+) -> io::Result<InputSet> {
+    let mut header: Option<Vec<String>> = None;
+    let mut network_index = 0;
+    let mut label_names = Vec::new();
+    let mut records = Vec::new();
+
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim_start_matches('\u{feff}');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_fields(line, delimiter);
+        if header.is_none() {
+            network_index = find_column(&fields, column)?;
+            label_names = fields
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != network_index)
+                .map(|(_, name)| name.clone())
+                .collect();
+            header = Some(fields);
+            continue;
+        }
+
+        let network_text = fields.get(network_index).map(|s| s.as_str()).unwrap_or("");
+        let network = parse_network(network_text, number + 1)?;
+        let header_len = header.as_ref().map(|h| h.len()).unwrap_or(0);
+        let labels = (0..header_len)
+            .filter(|index| *index != network_index)
+            .map(|index| fields.get(index).cloned().unwrap_or_default())
+            .collect();
+        records.push(InputRecord { network, labels });
+    }
+    Ok(InputSet { label_names, records })
+}
+
+// JSON值转换成标签文本：字符串取原值，null为空，其他类型取JSON文本
+fn json_label(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn read_json_lines<I: Iterator<Item = io::Result<String>>>(
+    lines: I,
+    column: &str,
+) -> io::Result<InputSet> {
+    let mut label_names: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("第{}行：JSON格式错误：{}", number + 1, e),
+            )
+        })?;
+        let object = match value {
+            Value::Object(object) => object,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("第{}行：不是JSON对象", number + 1),
+                ))
+            }
+        };
+        let network_text = match object.get(column) {
+            Some(Value::String(s)) => s.clone(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("第{}行：缺少字符串字段 {}", number + 1, column),
+                ))
+            }
+        };
+        let network = parse_network(network_text.trim(), number + 1)?;
+        // 标签列按首次出现的顺序排列
+        for key in object.keys() {
+            if key != column && !label_names.contains(key) {
+                label_names.push(key.clone());
+            }
+        }
+        rows.push((network, object));
+    }
+
+    let records = rows
+        .into_iter()
+        .map(|(network, object)| InputRecord {
+            network,
+            labels: label_names
+                .iter()
+                .map(|name| object.get(name).map(json_label).unwrap_or_default())
+                .collect(),
+        })
+        .collect();
+    Ok(InputSet { label_names, records })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_fields_keeps_separator_inside_quotes() {
+        assert_eq!(split_fields(r#"1.0.0.0/24,"Example, Inc.",us"#, ','), ["1.0.0.0/24", "Example, Inc.", "us"]);
+        assert_eq!(split_fields("1.0.0.0/24\t\"a\tb\"\tc", '\t'), ["1.0.0.0/24", "a\tb", "c"]);
+    }
+
+    #[test]
+    fn split_fields_unescapes_quotes_and_keeps_empty_fields() {
+        assert_eq!(split_fields(r#""say ""hi""",,x"#, ','), [r#"say "hi""#, "", "x"]);
+        assert_eq!(split_fields(" a , b ", ','), ["a", "b"]);
+    }
+
+    #[test]
+    fn delimited_labels_follow_header() {
+        let lines = ["provider,cidr,region", r#""Example, Inc.",1.0.0.0/24,"us,east""#, "other,2.0.0.0/16"];
+        let input = read_delimited(lines.iter().map(|line| Ok(line.to_string())), ',', "cidr").unwrap();
+        assert_eq!(input.label_names, ["provider", "region"]);
+        assert_eq!(input.records.len(), 2);
+        assert_eq!(input.records[0].labels, ["Example, Inc.", "us,east"]);
+        assert_eq!(input.records[1].network.to_string(), "2.0.0.0/16");
+        assert_eq!(input.records[1].labels, ["other", ""]);
+    }
+}
@@ -0,0 +1,24 @@
+// 生成IPv4地址的各个模块；命令行程序在 main.rs 中，基准测试也通过这里使用这些模块
+pub mod append;
+pub mod atomic;
+pub mod bitmap;
+pub mod cli;
+pub mod columnar;
+pub mod dotted;
+pub mod exclude;
+pub mod export;
+pub mod generate;
+pub mod input;
+pub mod manifest;
+pub mod naming;
+pub mod output;
+pub mod parallel;
+pub mod ports;
+pub mod progress;
+pub mod ranges;
+pub mod reverse;
+pub mod split;
+pub mod sqlite;
+pub mod template;
+pub mod verify;
+pub mod write;
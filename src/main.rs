@@ -1,91 +1,73 @@
-extern crate ipnetwork;
-
+use generate_all_ipv4_addresses::bitmap::AddressSet;
+use generate_all_ipv4_addresses::cli::{self, Command, Options, SetOperation, SplitMode};
+use generate_all_ipv4_addresses::generate::{generate_ips, generate_unique_ips, RecordSet};
+use generate_all_ipv4_addresses::input::{self, InputFormat, InputSet};
+use generate_all_ipv4_addresses::manifest::{self, Manifest};
+use generate_all_ipv4_addresses::export::{self, ExportSet};
+use generate_all_ipv4_addresses::naming::FileNaming;
+use generate_all_ipv4_addresses::progress::Progress;
+use generate_all_ipv4_addresses::output::{self, OutputFormat, RecordFormat};
+use generate_all_ipv4_addresses::ranges::{self, Range4};
+use generate_all_ipv4_addresses::split::{self, FilePlan};
+use generate_all_ipv4_addresses::template::Template;
+use generate_all_ipv4_addresses::{append, atomic, columnar, exclude, reverse, sqlite, verify, write};
 use ipnetwork::IpNetwork;
-use std::path::Path;
+use std::env;
 use std::time::Instant;
-use std::sync::{Mutex, Arc};
-use std::fs::File;
-use std::io::{self, Write, BufRead};
-use std::thread;
-
-
-// 生成IP地址列表
-fn generate_ips(cidrs: &Vec<String>) -> io::Result<Vec<String>> {
-    let mut ips = Vec::new();
-
-    for cidr in cidrs {
-        let cidr_network = cidr.parse::<IpNetwork>().map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("解析{}时出现 {} 错误！", cidr, e),
-            )
-        })?;
-
-        match cidr_network {
-            IpNetwork::V4(v4_network) => {
-                let ip_iter = v4_network.iter();
-                let ip_strings: Vec<String> = ip_iter.map(|ip| ip.to_string()).collect();
-                ips.extend(ip_strings);
-            }
-            IpNetwork::V6(_) => {
-                println!("IPv6 CIDR范围不受支持。");
-            }
-        }
-    }
-    Ok(ips)
-}
-
-// 将IP地址列表写入文件
-fn write_ips_to_file(output_file: &str, ips: Vec<String>) -> io::Result<()> {
-    let file = File::create(output_file)?;
-    let file = Arc::new(Mutex::new(file)); // 使用互斥锁包装文件对象
-    let mut handles = vec![];
-
-    for ip in ips {
-        let file = Arc::clone(&file); // 克隆互斥锁的Arc
-        let handle = thread::spawn(move || {
-            // 在线程中使用互斥锁来写入文件
-            let mut file = file.lock().unwrap();
-            writeln!(&mut *file, "{}", ip).unwrap();
-        });
-        handles.push(handle);
-    }
-
-    for handle in handles {
-        handle.join().unwrap();
-    }
+use std::fs;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+
+// 只把记录写入标准输出，用于管道；不写清单，也不清理上次的文件
+fn write_to_stdout(ips: &RecordSet, format: &RecordFormat, progress: &Progress) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut writer = BufWriter::with_capacity(256 * 1024, stdout.lock());
+    progress.start_write(ips.len() as u64, 1);
+    output::write_records(&mut writer, ips, slice::from_ref(&(0..ips.len())), format, true, Some(progress))?;
+    progress.file_done();
+    progress.clear();
     Ok(())
 }
 
-// 定义一个函数，获取用户输入的分割数
+// 定义一个函数，获取用户输入的分割数，必须大于0
 fn get_input_numbers() -> usize {
     loop {
         let mut input = String::new();
         io::stdin().read_line(&mut input).expect("无法读取输入");
         match input.trim().parse::<usize>() {
-            Ok(num) => return num,
-            Err(_) => {
-                print!("请输入有效的数字：");
+            Ok(num) if num > 0 => return num,
+            _ => {
+                print!("请输入大于0的数字：");
                 io::stdout().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
             }
         }
     }
 }
 
-
-// 从外部文件中读取CIDR地址
-fn read_cidr_from_file(filename: &str) -> io::Result<Vec<String>> {
-    let path = Path::new(filename);
-    let file = File::open(path)?;
-
-    let mut cidrs = Vec::new();
-
-    for line in io::BufReader::new(file).lines() {
-        if let Ok(line) = line {
-            cidrs.push(line.trim().to_string());
-        }
+// 删除上次生成的文件前确认：--force 时直接删除，没有键盘输入时不删除
+fn confirm_clean(files: &[String], options: &Options) -> bool {
+    if options.force {
+        return true;
+    }
+    if !io::stdin().is_terminal() {
+        eprintln!("上次运行生成的{}个文件未删除(使用 --force 删除，--keep-previous 保留)", files.len());
+        return false;
+    }
+    eprintln!("上次运行生成了以下{}个文件：", files.len());
+    for name in files.iter().take(10) {
+        eprintln!("  {}", name);
     }
-    Ok(cidrs)
+    if files.len() > 10 {
+        eprintln!("  ...");
+    }
+    eprint!("是否删除这些文件？(y/N)：");
+    io::stderr().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("读取输入失败");
+    matches!(input.trim(), "y" | "Y" | "yes")
 }
 
 fn wait_for_enter() {
@@ -97,7 +79,7 @@ fn wait_for_enter() {
 
 
 // 选择写入文件的模式（等份分割、最大文件上限、全部写入）
-fn get_write_mode() -> u32 {
+fn get_write_mode() -> SplitMode {
     println!("选择写入txt文件的模式：\n");
     println!("1. 等份分割写入txt文件");
     println!("2. 设置txt文件写入上限");
@@ -110,90 +92,489 @@ fn get_write_mode() -> u32 {
         io::stdin().read_line(&mut input).expect("读取用户输入失败");
 
         match input.trim().parse() {
-            Ok(1) | Ok(2) | Ok(3) => return input.trim().parse().unwrap(),
+            Ok(1) => {
+                print!("设置要分割成多少份文件存储(等份切割)：");
+                io::stdout().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
+                return SplitMode::Equal(get_input_numbers()); // 分割多少份文件存储
+            }
+            Ok(2) => {
+                print!("设置每个文件的最多写入多少行：");
+                io::stdout().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
+                return SplitMode::MaxLines(get_input_numbers()); // 最大行数
+            }
+            Ok(3) => return SplitMode::All,
             _ => {}
         }
     }
 }
 
-// 等份分割写入txt文件
-fn write_equally_to_files(ips: &Vec<String>) {
-    print!("设置要分割成多少份文件存储(等份切割)：");
-    io::stdout().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
-    let num_segments = get_input_numbers(); // 分割多少份文件存储
+// 写入前显示每个文件的记录数，连续相同的记录数合并显示
+fn print_plan_preview(plans: &[FilePlan]) {
+    eprintln!("将写入{}个文件：", plans.len());
+    let mut start = 0;
+    while start < plans.len() {
+        let lines = split::plan_len(&plans[start]);
+        let mut end = start + 1;
+        while end < plans.len() && split::plan_len(&plans[end]) == lines {
+            end += 1;
+        }
+        if end - start == 1 {
+            eprintln!("  第{}个文件：{}条记录", start + 1, lines);
+        } else {
+            eprintln!("  第{}-{}个文件：每个{}条记录", start + 1, end, lines);
+        }
+        start = end;
+    }
+}
 
-    let ips_per_segment = ips.len() / num_segments;
+// 根据命令行参数创建记录的输出格式，模板和标签列在这里一次性检查
+fn build_format<'a>(input: &'a InputSet, options: &Options) -> io::Result<RecordFormat<'a>> {
+    let mut format = RecordFormat::new(options.format, input, &options.with_labels)?;
+    format.separator = options.separator.clone();
+    format.line_ending = options.line_ending.clone();
+    format.header = options.header;
+    format.ports = !options.ports.is_empty();
+    format.row_group_size = options.row_group_size;
+    format.threads = options.threads;
+    if format.ports && format.format == OutputFormat::Reverse && options.template.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "反向解析名称格式不能与 --ports 一起使用",
+        ));
+    }
+    if let Some(text) = &options.template {
+        let template = Template::parse(text, input)?;
+        if template.uses_port() && options.ports.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "模板中使用了 {port}，但没有通过 --ports 指定端口",
+            ));
+        }
+        format.template = Some(template);
+    }
+    match format.format {
+        OutputFormat::Sqlite => sqlite::check_columns(&format)?,
+        OutputFormat::Parquet => columnar::check_columns(&format)?,
+        _ => format.check_columns()?,
+    }
+    Ok(format)
+}
 
-    for segment in 0..num_segments {
-        let start = segment * ips_per_segment;
-        let end = if segment == num_segments - 1 {
-            ips.len()
-        } else {
-            (segment + 1) * ips_per_segment
-        };
+// 根据命令行参数创建输出目录和文件命名规则
+fn build_naming(input: &InputSet, format: &RecordFormat, options: &Options) -> io::Result<FileNaming> {
+    let split_label = match &options.split {
+        SplitMode::Label(name) => Some(output::find_label(input, name)?),
+        _ => None,
+    };
+    let prefix = match options.split {
+        SplitMode::Prefix(prefix) => Some(prefix),
+        _ => None,
+    };
+    let template = match (&options.file_name, &options.split) {
+        (Some(template), _) => template.as_str(),
+        (None, SplitMode::Label(_)) => "ip_{label}.{ext}",
+        (None, SplitMode::Cidr) => "ip_{cidr}.{ext}",
+        (None, SplitMode::Prefix(_)) => "ip_{prefix}.{ext}",
+        (None, _) => "ip_{index}.{ext}",
+    };
+    let extension = format.format.extension();
+    FileNaming::new(&options.output_dir, template, input, split_label, prefix, extension)
+}
 
-        let segment_ips = &ips[start..end];
-        let segment_output_file = format!("ip_{}.txt", segment + 1);
+// 按选择的模式计算每个文件包含的记录，并显示等份分割的预览和超过上限的提示
+fn plan_files(ips: &RecordSet, format: &RecordFormat, options: &Options) -> io::Result<Vec<FilePlan>> {
+    let (plans, oversized) = split::plan_files(ips, format, &options.split)?;
+    match &options.split {
+        SplitMode::Equal(num_segments) => {
+            if *num_segments > ips.len() {
+                eprintln!("分割份数{}大于记录数{}，只写入{}个文件", num_segments, ips.len(), ips.len());
+            }
+            print_plan_preview(&plans);
+        }
+        SplitMode::MaxBytes(max_bytes) if oversized > 0 => {
+            eprintln!("有{}条记录单独就超过了每个文件{}字节的上限，这些记录各自单独写入一个文件", oversized, max_bytes);
+        }
+        SplitMode::WholeBlocks { max_lines, prefix } if oversized > 0 => {
+            eprintln!("有{}个 /{} 网段超过了每个文件{}行的上限，这些网段各自单独写入一个文件", oversized, prefix, max_lines);
+        }
+        _ => {}
+    }
+    Ok(plans)
+}
 
-        if let Err(err) = write_ips_to_file(&segment_output_file, segment_ips.to_vec()) {
-            eprintln!("写入文件 {} 时出错：{}", segment_output_file, err);
+// 写入全部文件并保存清单，有文件写入失败时保存清单后返回错误
+fn write_files(
+    ips: &RecordSet,
+    plans: &[FilePlan],
+    paths: &[PathBuf],
+    format: &RecordFormat,
+    dir: &Path,
+    manifest: &mut Manifest,
+    progress: &Progress,
+) -> io::Result<()> {
+    // 写入前先保存未完成的清单，并把要写的文件名记入 leftover：
+    // 中途退出时下一次运行仍能清理这些文件，verify 也能发现这次生成没有完成
+    for path in paths {
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            if !manifest.leftover.iter().any(|leftover| leftover == name) {
+                manifest.leftover.push(name.to_string());
+            }
         }
     }
+    manifest.complete = false;
+    manifest.save(dir)?;
+    manifest.complete = write::write_plan(ips, plans, paths, format, manifest, progress);
+    manifest.save(dir)?;
+    // 清单已经记下了这次没有完成，再返回错误，让调用的脚本能从退出码知道输出不完整
+    if !manifest.complete {
+        return Err(io::Error::other("有文件写入失败，输出不完整"));
+    }
+    Ok(())
 }
 
-// txt文件的最大上限写入
-fn write_with_max_limit(ips: &Vec<String>) {
-    let mut ips_to_write = Vec::new(); // 用于储存待写入文件的IP地址
-    let mut lines_written = 1; // 初始行数为1，第一次写入文件，从第1行开始写入
-    let mut file_number = 0;
-    let mut max_lines_per_file: usize;
-    let mut current_file = format!("ip_{}.txt", file_number);
-    loop {
-        print!("设置每个文件的最多写入多少行(必须大于256行)：");
-        io::stdout().flush().expect("刷新输出缓冲区失败"); // 刷新输出缓冲区
-        max_lines_per_file = get_input_numbers(); // 最大行数
-        if max_lines_per_file >= 256 {
-            break; // 如果最大行数大于256，退出循环
+// 按选择的模式写入txt文件
+fn write_output(
+    ips: &RecordSet,
+    format: &RecordFormat,
+    naming: &FileNaming,
+    options: &Options,
+    progress: &Progress,
+) -> io::Result<()> {
+    // 先确定全部文件的路径，路径有冲突时不删除上次的文件
+    let plans = plan_files(ips, format, options)?;
+    let paths = write::file_paths(ips, &plans, format, naming, options, 0)?;
+    naming.create_dir()?;
+    let mut manifest = new_manifest(format.input(), options)?;
+    manifest.total_addresses = ips.ips().len() as u64;
+    manifest.total_records = ips.len() as u64;
+    manifest.unique = options.append;
+    if options.append {
+        // 按顺序合并时已有的地址全部重新写入：先写完新的文件和清单，再删除新清单中没有的旧文件，
+        // 写入失败时旧文件仍然保留，并记在清单的 leftover 中
+        // 没有清单时无法知道已有文件中有哪些地址，不覆盖这些文件
+        if Manifest::load(naming.dir())?.is_none() {
+            if let Some(path) = paths.iter().find(|path| path.exists()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} 已存在，但输出目录中没有清单 {}，无法确定已有的地址；请移走这个文件后再追加",
+                        path.display(),
+                        manifest::MANIFEST_FILE
+                    ),
+                ));
+            }
+        }
+        manifest.leftover = manifest::previous_files(naming.dir())?;
+        write_files(ips, &plans, &paths, format, naming.dir(), &mut manifest, progress)?;
+        if options.keep_previous {
+            return Ok(());
+        }
+        let removed = manifest::remove_leftover(naming.dir(), &mut manifest);
+        if removed > 0 {
+            eprintln!("已删除上次生成的{}个文件", removed);
+        }
+        return manifest.save(naming.dir());
+    }
+    let cleaned = manifest::clean_previous(naming.dir(), |files| {
+        !options.keep_previous && confirm_clean(files, options)
+    })?;
+    if cleaned.removed > 0 {
+        eprintln!("已删除上次生成的{}个文件", cleaned.removed);
+    }
+    manifest.leftover = cleaned.kept;
+
+    write_files(ips, &plans, &paths, format, naming.dir(), &mut manifest, progress)
+}
+
+// 追加模式：只把新的地址写入已有的输出，已有文件保持不变
+// 全部写入一个文件时追加到该文件末尾，分割写入时新文件的序号接在已有文件之后
+fn append_output(
+    ips: &RecordSet,
+    format: &RecordFormat,
+    naming: &FileNaming,
+    options: &Options,
+    existing: Manifest,
+    excluded: &[Range4],
+    progress: &Progress,
+) -> io::Result<()> {
+    let mut manifest = new_manifest(format.input(), options)?;
+    manifest.input.networks = append::merge_networks(&existing.input.networks, &manifest.input.networks);
+    let excluded = append::merged_exclusions(&existing, format.input(), excluded)?;
+    manifest.input.excluded = ranges::to_networks(&excluded).iter().map(|n| n.to_string()).collect();
+    manifest.total_addresses = existing.total_addresses + ips.ips().len() as u64;
+    manifest.total_records = existing.total_records + ips.len() as u64;
+    manifest.unique = true;
+    manifest.files = existing.files;
+    manifest.leftover = existing.leftover;
+
+    let plans = plan_files(ips, format, options)?;
+    let skip = match options.split {
+        SplitMode::All => 0,
+        _ => manifest.files.len(),
+    };
+    let paths = write::file_paths(ips, &plans, format, naming, options, skip)?;
+    write_files(ips, &plans, &paths, format, naming.dir(), &mut manifest, progress)
+}
+
+// 新的清单：记录运行参数和输入指纹
+fn new_manifest(input: &InputSet, options: &Options) -> io::Result<Manifest> {
+    let parameters = serde_json::to_value(options)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut input_info = manifest::describe_input(input, options.input.as_deref())?;
+    let excluded = exclude::load_exclusions(options)?;
+    input_info.excluded = ranges::to_networks(&excluded).iter().map(|n| n.to_string()).collect();
+    Ok(Manifest::new(env::args().collect(), parameters, input_info))
+}
+
+// 读取命令行指定的输入：优先使用输入文件，否则使用命令行中的CIDR
+fn load_input(options: &Options) -> io::Result<InputSet> {
+    match &options.input {
+        Some(filename) => {
+            let format = options
+                .input_format
+                .unwrap_or_else(|| InputFormat::from_path(filename));
+            input::read_input(filename, format, &options.network_column)
         }
+        None => input::from_cidrs(&options.cidrs),
     }
-    for ip in ips.iter() {
-        ips_to_write.push(ip.to_string()); // 克隆 IP 地址并添加到 ips_to_write
-        if lines_written >= max_lines_per_file {
-            file_number += 1;
-            current_file = format!("ip_{}.txt", file_number);
-            lines_written = 0; // 初始行数归零，写入文件后，在代码后面重新加1：lines_written += 1；
+}
 
-            // 调用函数写入txt文件中
-            if let Err(err) = write_ips_to_file(&current_file, ips_to_write.clone()) {
-                eprintln!("写入文件 {} 时出错：{}", current_file, err);
+// 追加模式：按已有输出的清单计算哪些地址已经写过，只写入新的地址
+fn run_append(options: &Options) -> io::Result<()> {
+    let input = load_input(options)?;
+    let format = build_format(&input, options)?;
+    let naming = build_naming(&input, &format, options)?;
+    if options.sorted {
+        append::check_sorted(options, format.template.as_ref())?;
+    }
+    let excluded = exclude::load_exclusions(options)?;
+    let progress = Progress::new(options.progress);
+    let existing = match Manifest::load(naming.dir())? {
+        Some(existing) => existing,
+        None => {
+            // 还没有输出时与普通的生成相同，只是去掉重复的地址
+            let mut ips = generate_unique_ips(&input, &excluded, options.threads, Some(&progress))?;
+            if options.sorted {
+                ips.sort_by_key(|generated| generated.ip);
             }
-            ips_to_write.clear();
+            progress.clear();
+            eprintln!("没有找到已有的输出，生成的IPv4地址共{}个", ips.len());
+            return write_output(&RecordSet::new(&ips, &options.ports), &format, &naming, options, &progress);
         }
+    };
+    append::check_existing(&existing, options)?;
+
+    let start_generate_time = Instant::now();
+    let covered = append::covered_ranges(&existing)?;
+    let skipped = ranges::merge([covered.as_slice(), excluded.as_slice()].concat());
+    let ips = generate_unique_ips(&input, &skipped, options.threads, Some(&progress))?;
+    progress.clear();
+    eprintln!(
+        "已有输出中有{}个IPv4地址，新增{}个，消耗时间：{:?}",
+        existing.total_addresses,
+        ips.len(),
+        start_generate_time.elapsed()
+    );
+    if ips.is_empty() {
+        eprintln!("没有需要追加的新地址");
+        return Ok(());
+    }
+
+    let start_write_time = Instant::now();
+    if options.sorted {
+        // 已有的CIDR和新的输入一起重新生成，排序后重新写入全部文件
+        let combined = append::combined_input(&existing, &input)?;
+        let mut ips = generate_unique_ips(&combined, &excluded, options.threads, Some(&progress))?;
+        ips.sort_by_key(|generated| generated.ip);
+        let format = build_format(&combined, options)?;
+        write_output(&RecordSet::new(&ips, &options.ports), &format, &naming, options, &progress)?;
+    } else {
+        let records = RecordSet::new(&ips, &options.ports);
+        append_output(&records, &format, &naming, options, existing, &excluded, &progress)?;
+    }
+    eprintln!("写入txt文件，消耗时间: {:?}", start_write_time.elapsed());
+    Ok(())
+}
+
+// 命令行模式：不需要任何键盘输入
+fn run(options: &Options) -> io::Result<()> {
+    if options.append {
+        return run_append(options);
+    }
+    let input = load_input(options)?;
+    let format = build_format(&input, options)?;
+    let naming = build_naming(&input, &format, options)?;
+    let start_generate_time = Instant::now();
+    let excluded = exclude::load_exclusions(options)?;
+    let progress = Progress::new(options.progress);
+    let ips = generate_ips(&input, &excluded, options.threads, Some(&progress))?;
+    progress.clear();
+    eprintln!("生成的IPv4地址共{}个，消耗时间：{:?}", ips.len(), start_generate_time.elapsed());
+    let records = RecordSet::new(&ips, &options.ports);
+    if !options.ports.is_empty() {
+        eprintln!("每个IP地址{}个端口，共{}条记录", options.ports.len(), records.len());
+    }
+
+    let start_write_time = Instant::now();
+    if options.output == cli::STDOUT {
+        write_to_stdout(&records, &format, &progress)?;
+    } else {
+        write_output(&records, &format, &naming, options, &progress)?;
+    }
+    eprintln!("写入txt文件，消耗时间: {:?}", start_write_time.elapsed());
+    Ok(())
+}
+
+// 校验模式：按清单检查输出目录，指定了输入时与该输入对比
+fn run_verify(options: &Options) -> io::Result<bool> {
+    let input = match (&options.input, options.cidrs.is_empty()) {
+        (None, true) => None,
+        _ => Some(load_input(options)?),
+    };
+    let dir = if options.output_dir.is_empty() { "." } else { options.output_dir.as_str() };
+    verify::verify(Path::new(dir), input.as_ref())
+}
 
-        lines_written += 1; // 初始行数的值+1，意为从新文件的第1行开始写
+// 反向区模式：把覆盖输入所需的最少的反向区名称写入标准输出，每行一个
+fn run_zones(options: &Options) -> io::Result<()> {
+    let input = load_input(options)?;
+    let networks: Vec<IpNetwork> = input.records.iter().map(|record| record.network).collect();
+    let zones = reverse::reverse_zones(&networks);
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    for zone in &zones {
+        writeln!(writer, "{}", zone)?;
     }
+    writer.flush()?;
+    eprintln!("共{}个反向区", zones.len());
+    Ok(())
+}
 
-    // 最后一次，写入剩余的 IP 地址
-    if !ips_to_write.is_empty() {
-        if let Err(err) = write_ips_to_file(&current_file, ips_to_write) {
-            eprintln!("写入文件 {} 时出错：{}", current_file, err);
+// 输入中IPv4网络覆盖的区间，已排序、已合并
+fn input_ranges(input: &InputSet) -> Vec<Range4> {
+    let mut targets = Vec::new();
+    for record in &input.records {
+        match record.network {
+            IpNetwork::V4(network) => targets.push(ranges::network_range(network)),
+            IpNetwork::V6(_) => eprintln!("IPv6 CIDR范围不受支持。"),
         }
     }
+    ranges::merge(targets)
 }
 
-// 全部写入到一个txt文件中
-fn write_all_to_single_file(output_file: &str, ips: &Vec<String>) {
-    if let Err(err) = write_ips_to_file(&output_file, (&ips).to_vec()) {
-        eprintln!("写入文件 {} 时出错：{}", output_file, err);
+// 导出模式：合并输入中的CIDR，按 --target 的格式导出
+// 只有一个文件时写入标准输出，有多个文件(例如 nmap 的目标和排除文件)时写入输出目录
+fn run_export(options: &Options) -> io::Result<()> {
+    let input = load_input(options)?;
+    let excluded = exclude::load_exclusions(options)?;
+    let set = ExportSet::new(&input_ranges(&input), &excluded);
+    let target = options.target.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "没有指定导出格式")
+    })?;
+    let files = export::export(target, &set, options)?;
+    eprintln!(
+        "输入{}个CIDR，合并后{}个，排除{}个",
+        input.records.len(),
+        set.targets.len(),
+        set.excluded.len()
+    );
+
+    if let [file] = files.as_slice() {
+        let stdout = io::stdout();
+        let mut writer = stdout.lock();
+        writer.write_all(&file.content)?;
+        return writer.flush();
+    }
+    let dir = Path::new(&options.output_dir);
+    if !options.output_dir.is_empty() {
+        fs::create_dir_all(dir)?;
+    }
+    for file in &files {
+        let path = dir.join(file.name);
+        atomic::write(&path, &file.content)?;
+        eprintln!("已写入 {}", path.display());
     }
+    Ok(())
 }
 
+// 把集合保存到 -o 指定的文件，-o 为 - 时写入标准输出
+fn save_set(set: &AddressSet, options: &Options) -> io::Result<()> {
+    if options.output == cli::STDOUT {
+        let stdout = io::stdout();
+        let mut writer = BufWriter::new(stdout.lock());
+        set.write_to(&mut writer)?;
+        writer.flush()?;
+    } else {
+        set.save(Path::new(&options.output))?;
+        eprintln!("已写入 {}", options.output);
+    }
+    eprintln!("集合中共{}个地址，{}字节", set.count(), set.serialized_size());
+    Ok(())
+}
 
-fn main() {
-    println!("本程序：用于生成IPv4 CIDR范围内的所有IP地址！并多线程写入txt文件。");
+// 集合模式：建立、合并集合文件，或者查询、转换一个集合文件；返回 false 表示有地址不在集合中
+fn run_set(options: &Options) -> io::Result<bool> {
+    let load_all = || -> io::Result<Vec<AddressSet>> {
+        options.cidrs.iter().map(|path| AddressSet::load(Path::new(path))).collect()
+    };
+    match options.set_operation {
+        SetOperation::Build => {
+            let input = load_input(options)?;
+            let excluded = exclude::load_exclusions(options)?;
+            let addresses = ranges::difference(&input_ranges(&input), &excluded);
+            save_set(&AddressSet::from_ranges(&addresses), options)?;
+        }
+        SetOperation::Union | SetOperation::Intersect => {
+            let mut sets = load_all()?.into_iter();
+            let mut result = sets.next().unwrap_or_default();
+            for set in sets {
+                match options.set_operation {
+                    SetOperation::Union => result.union(&set),
+                    _ => result.intersect(&set),
+                }
+            }
+            save_set(&result, options)?;
+        }
+        SetOperation::Contains => {
+            let set = AddressSet::load(Path::new(&options.cidrs[0]))?;
+            let stdout = io::stdout();
+            let mut writer = stdout.lock();
+            let mut all = true;
+            for text in &options.cidrs[1..] {
+                let ip: Ipv4Addr = text.trim().parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, format!("无效的IPv4地址：{}", text))
+                })?;
+                let contained = set.contains(ip);
+                all &= contained;
+                writeln!(writer, "{} {}", ip, if contained { "是" } else { "否" })?;
+            }
+            return Ok(all);
+        }
+        SetOperation::Cidrs | SetOperation::Text => {
+            let set = AddressSet::load(Path::new(&options.cidrs[0]))?;
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            if options.set_operation == SetOperation::Cidrs {
+                for network in ranges::to_networks(&set.ranges()) {
+                    writeln!(writer, "{}", network)?;
+                }
+            } else {
+                for ip in set.addresses() {
+                    writeln!(writer, "{}", ip)?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(true)
+}
+
+// 交互模式：在命令行窗口中输入CIDR并选择写入模式
+fn run_interactive() {
+    println!("本程序：用于生成IPv4 CIDR范围内的所有IP地址！并写入txt文件。");
 
     let external_cidr_filename = "ips-v4.txt";
-    let output_file = "ip.txt"; // 全部写入，就写入这个文件中
+    let mut options = Options::default(); // 全部写入，就写入 ip.txt 文件中
     println!("------------------------------------------------------------------");
     println!("请在下面输入一个或多个CIDR，输入多个时请用空格隔开；");
     println!("(检查输入的内容不合法，就使用外部文件ips-v4.txt的CIDR)");
@@ -203,13 +584,15 @@ fn main() {
     let mut input = String::new();
     io::stdin().read_line(&mut input).expect("无法读取输入");
     println!("------------------------------------------------------------------");
-    let mut cidrs: Vec<String> = input.trim().split_whitespace().map(|s| s.to_string()).collect();
+    let cidrs: Vec<String> = input.split_whitespace().map(|s| s.to_string()).collect();
 
-    if cidrs.is_empty() || cidrs.iter().any(|cidr| cidr.parse::<IpNetwork>().is_err()) {
+    let input = if cidrs.is_empty() || cidrs.iter().any(|cidr| cidr.parse::<IpNetwork>().is_err()) {
         // 命令行窗口中，可以输入一个、多个CIDR，输入多个CIDR用空格隔开，其他情况就使用外部ips-v4.txt文件中的CIDR
-        match read_cidr_from_file(external_cidr_filename) {
-            Ok(external_cidrs) => {
-                cidrs = external_cidrs;
+        let format = InputFormat::from_path(external_cidr_filename);
+        match input::read_input(external_cidr_filename, format, &options.network_column) {
+            Ok(external_input) => {
+                options.input = Some(external_cidr_filename.to_string());
+                external_input
             }
             Err(err) => {
                 eprintln!("读取外部{}文件出错：{}", external_cidr_filename, err);
@@ -217,21 +600,34 @@ fn main() {
                 std::process::exit(1); // 立即退出程序，返回退出码 1 表示错误
             }
         }
-    }
+    } else {
+        match input::from_cidrs(&cidrs) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("生成IP地址时出错：{}", err);
+                wait_for_enter();
+                std::process::exit(1);
+            }
+        }
+    };
     // 记录开始时间
     let start_generate_time = Instant::now();
     let start_write_time: Instant;
     println!("开始生成IPv4地址...");
-    match generate_ips(&cidrs) {
+    let progress = Progress::new(options.progress);
+    match generate_ips(&input, &[], options.threads, Some(&progress)) {
         Ok(ips) => {
+            progress.clear();
             println!("生成的IPv4地址共{}个，消耗时间：{:?}", ips.len(),start_generate_time.elapsed());
             println!("------------------------------------------------------------------");
-            let mode = get_write_mode(); // 获取用户选择的写入模式
+            options.split = get_write_mode(); // 获取用户选择的写入模式
             start_write_time = Instant::now();
-            match mode {
-                1 => write_equally_to_files(&ips),
-                2 => write_with_max_limit(&ips),
-                _ => write_all_to_single_file(&output_file, &ips),
+            let result = build_format(&input, &options).and_then(|format| {
+                let naming = build_naming(&input, &format, &options)?;
+                write_output(&RecordSet::new(&ips, &[]), &format, &naming, &options, &progress)
+            });
+            if let Err(err) = result {
+                eprintln!("写入txt文件时出错：{}", err);
             }
             println!("------------------------------------------------------------------");
             println!("写入txt文件，消耗时间: {:?}", start_write_time.elapsed());
@@ -245,3 +641,75 @@ fn main() {
     println!();
     wait_for_enter();
 }
+
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        run_interactive();
+        return;
+    }
+
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("使用 --help 查看帮助");
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        cli::print_help();
+        return;
+    }
+    if options.command == Command::Verify {
+        match run_verify(&options) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(err) => {
+                eprintln!("校验时出错：{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.command == Command::Zones {
+        if let Err(err) = run_zones(&options) {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                eprintln!("生成反向区时出错：{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.command == Command::Set {
+        match run_set(&options) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(err) => {
+                eprintln!("处理地址集合时出错：{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.command == Command::Export {
+        if let Err(err) = run_export(&options) {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                eprintln!("导出时出错：{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Err(err) = run(&options) {
+        // 管道另一端提前退出(例如 head)时正常结束
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            return;
+        }
+        eprintln!("生成IP地址时出错：{}", err);
+        std::process::exit(1);
+    }
+}
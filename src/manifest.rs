@@ -0,0 +1,389 @@
+use crate::atomic;
+use crate::input::InputSet;
+use crate::output::RecordStats;
+use crate::ranges::{self, Range4};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+// 清单文件名，保存在输出目录中
+pub const MANIFEST_FILE: &str = "ip_manifest.json";
+
+// 清单格式的版本号，字段有不兼容的变化时加1
+pub const MANIFEST_VERSION: u32 = 1;
+
+// 清单中一个输出文件的信息
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub name: String,          // 相对于输出目录的文件名
+    pub lines: u64,            // 换行符的数量，与 wc -l 一致
+    pub records: u64,          // 记录数，不含表头
+    pub bytes: u64,
+    pub first: Option<String>, // 第一条记录的IP地址
+    pub last: Option<String>,  // 最后一条记录的IP地址
+    pub cidrs: Vec<String>,    // 文件中的记录来自哪些输入CIDR
+    pub sha256: String,
+}
+
+// 输入的指纹，用来判断两次运行的输入是否相同
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputInfo {
+    pub source: String,         // 输入文件名，没有输入文件时为 "command line"
+    pub sha256: Option<String>, // 输入文件内容的SHA-256
+    pub fingerprint: String,    // 解析后的网络地址和标签的SHA-256，与文件的排版无关
+    pub label_names: Vec<String>,
+    pub networks: Vec<String>,
+    // 生成时排除的地址(--exclude、--exclude-bogons 等)，已合并成最少的CIDR
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
+}
+
+// 一次运行的清单：运行参数、输入指纹，以及生成的每个文件
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub generator: String,
+    #[serde(default)]
+    pub created: u64, // Unix时间戳(秒)
+    #[serde(default)]
+    pub command_line: Vec<String>,
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+    #[serde(default)]
+    pub input: InputInfo,
+    #[serde(default)]
+    pub total_addresses: u64,
+    #[serde(default)]
+    pub total_records: u64,
+    // 全部文件写完后才为 true；为 false 表示生成过程中途退出，文件可能不全
+    #[serde(default = "default_complete")]
+    pub complete: bool,
+    // 为 true 时每条记录只出现一次(追加模式会去掉重复的地址)
+    #[serde(default)]
+    pub unique: bool,
+    pub files: Vec<FileEntry>,
+    // 以前运行生成、用户选择暂不删除的文件，下一次清理时一并删除
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leftover: Vec<String>,
+}
+
+// 旧版本的清单只在全部写完后保存，没有这个字段时视为已完成
+fn default_complete() -> bool {
+    true
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// 计算输入的指纹；source 为输入文件名
+pub fn describe_input(input: &InputSet, source: Option<&str>) -> io::Result<InputInfo> {
+    let mut hasher = Sha256::new();
+    hasher.update(input.label_names.join("\t").as_bytes());
+    hasher.update(b"\n");
+    let mut networks = Vec::with_capacity(input.records.len());
+    for record in &input.records {
+        let network = record.network.to_string();
+        hasher.update(network.as_bytes());
+        for label in &record.labels {
+            hasher.update(b"\t");
+            hasher.update(label.as_bytes());
+        }
+        hasher.update(b"\n");
+        networks.push(network);
+    }
+
+    let sha256 = match source {
+        Some(path) => Some(to_hex(&Sha256::digest(fs::read(path)?))),
+        None => None,
+    };
+    Ok(InputInfo {
+        source: source.unwrap_or("command line").to_string(),
+        sha256,
+        fingerprint: to_hex(&hasher.finalize()),
+        label_names: input.label_names.clone(),
+        networks,
+        excluded: Vec::new(),
+    })
+}
+
+// 写入时同时计算字节数、行数和SHA-256
+pub struct DigestWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    bytes: u64,
+    lines: u64,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W) -> DigestWriter<W> {
+        DigestWriter { inner, hasher: Sha256::new(), bytes: 0, lines: 0 }
+    }
+
+    // 返回内部的写入器和 (字节数, 行数, SHA-256)
+    pub fn finish(self) -> (W, (u64, u64, String)) {
+        (self.inner, (self.bytes, self.lines, to_hex(&self.hasher.finalize())))
+    }
+}
+
+// 已写完的文件的 (字节数, 行数, SHA-256)，用于不经过 DigestWriter 写入的文件
+pub fn digest_file(path: &Path) -> io::Result<(u64, u64, String)> {
+    let mut writer = DigestWriter::new(io::sink());
+    io::copy(&mut File::open(path)?, &mut writer)?;
+    Ok(writer.finish().1)
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let written_bytes = &buf[..written];
+        self.hasher.update(written_bytes);
+        self.bytes += written as u64;
+        self.lines += written_bytes.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Manifest {
+    pub fn new(
+        command_line: Vec<String>,
+        parameters: serde_json::Value,
+        input: InputInfo,
+    ) -> Manifest {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Manifest {
+            version: MANIFEST_VERSION,
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            created,
+            command_line,
+            parameters,
+            input,
+            ..Manifest::default()
+        }
+    }
+
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(MANIFEST_FILE)
+    }
+
+    // 读取输出目录中的清单，不存在时返回 None
+    pub fn load(dir: &Path) -> io::Result<Option<Manifest>> {
+        let text = match fs::read_to_string(Manifest::path(dir)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let manifest = serde_json::from_str(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("清单文件 {} 格式错误：{}", MANIFEST_FILE, e),
+            )
+        })?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        atomic::write(&Manifest::path(dir), (text + "\n").as_bytes())
+    }
+
+    // 清单中与 path 同名的文件
+    pub fn find_file(&self, path: &Path) -> Option<&FileEntry> {
+        let name = path.file_name().and_then(|name| name.to_str())?;
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    // 记录一个已写入的文件，清单中已有同名文件时替换它
+    pub fn add(
+        &mut self,
+        path: &Path,
+        stats: &RecordStats,
+        digest: (u64, u64, String),
+        input: &InputSet,
+    ) {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => return,
+        };
+        let (bytes, lines, sha256) = digest;
+        self.leftover.retain(|leftover| *leftover != name);
+        self.files.retain(|file| file.name != name);
+        self.files.push(FileEntry {
+            name,
+            lines,
+            records: stats.records,
+            bytes,
+            first: stats.first.map(|ip| ip.to_string()),
+            last: stats.last.map(|ip| ip.to_string()),
+            cidrs: stats
+                .sources
+                .iter()
+                .map(|&source| input.records[source].network.to_string())
+                .collect(),
+            sha256,
+        });
+    }
+}
+
+impl Manifest {
+    // 追加写入的文件：刚记录的条目只统计了新的记录，把原有部分的记录数、首地址和CIDR合并进来
+    pub fn merge_previous(&mut self, previous: FileEntry) {
+        let entry = match self.files.iter_mut().find(|file| file.name == previous.name) {
+            Some(entry) => entry,
+            None => return,
+        };
+        entry.records += previous.records;
+        if previous.first.is_some() {
+            entry.first = previous.first;
+        }
+        if entry.last.is_none() {
+            entry.last = previous.last;
+        }
+        let mut cidrs = previous.cidrs;
+        for cidr in entry.cidrs.drain(..) {
+            if !cidrs.contains(&cidr) {
+                cidrs.push(cidr);
+            }
+        }
+        entry.cidrs = cidrs;
+    }
+}
+
+impl InputInfo {
+    // 解析清单中记录的CIDR
+    pub fn parse_networks(&self) -> io::Result<Vec<IpNetwork>> {
+        self.networks
+            .iter()
+            .map(|text| text.parse::<IpNetwork>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("清单中的CIDR无法解析：{}", e))
+            })
+    }
+
+    // 解析清单中记录的排除地址，返回已排序、已合并的区间
+    pub fn parse_excluded(&self) -> io::Result<Vec<Range4>> {
+        let mut excluded = Vec::new();
+        for text in &self.excluded {
+            let network = text.parse::<Ipv4Network>().map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("清单中的排除地址无法解析：{}", e))
+            })?;
+            excluded.push(ranges::network_range(network));
+        }
+        Ok(ranges::merge(excluded))
+    }
+}
+
+// 清单中只允许出现输出目录下的文件名，防止删除目录之外的文件
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains('/')
+        && !name.contains('\\')
+        && name != MANIFEST_FILE
+}
+
+// 清理上一次运行的结果
+#[derive(Debug, Default)]
+pub struct CleanResult {
+    pub removed: usize,
+    pub kept: Vec<String>, // 没有删除的文件，需要记入新的清单
+}
+
+// 删除上一次运行生成的文件，confirm 返回 false 时不删除
+pub fn clean_previous<F: FnOnce(&[String]) -> bool>(
+    dir: &Path,
+    confirm: F,
+) -> io::Result<CleanResult> {
+    let manifest = match Manifest::load(dir)? {
+        Some(manifest) => manifest,
+        None => return Ok(CleanResult::default()),
+    };
+    let mut files: Vec<String> = Vec::new();
+    let names = manifest.files.into_iter().map(|file| file.name);
+    for name in names.chain(manifest.leftover) {
+        if !is_plain_file_name(&name) {
+            continue;
+        }
+        // 中途退出时留下的临时文件没有用处，直接删除
+        let _ = fs::remove_file(atomic::temp_path(&dir.join(&name)));
+        if dir.join(&name).is_file() && !files.contains(&name) {
+            files.push(name);
+        }
+    }
+    if files.is_empty() {
+        return Ok(CleanResult::default());
+    }
+    if !confirm(&files) {
+        return Ok(CleanResult { removed: 0, kept: files });
+    }
+
+    let mut result = CleanResult::default();
+    for name in files {
+        match fs::remove_file(dir.join(&name)) {
+            Ok(()) => result.removed += 1,
+            Err(err) => {
+                eprintln!("删除文件 {} 失败: {}", name, err);
+                result.kept.push(name);
+            }
+        }
+    }
+    fs::remove_file(Manifest::path(dir))?;
+    Ok(result)
+}
+
+// 上一次运行生成的文件(包括以前未删除的文件)，只返回输出目录中实际存在的文件
+// 先写新文件、后删旧文件时使用：这些文件名先记入新清单的 leftover，写入失败时仍能在下次清理
+pub fn previous_files(dir: &Path) -> io::Result<Vec<String>> {
+    let manifest = match Manifest::load(dir)? {
+        Some(manifest) => manifest,
+        None => return Ok(Vec::new()),
+    };
+    let mut files: Vec<String> = Vec::new();
+    let names = manifest.files.into_iter().map(|file| file.name);
+    for name in names.chain(manifest.leftover) {
+        if is_plain_file_name(&name) && dir.join(&name).is_file() && !files.contains(&name) {
+            files.push(name);
+        }
+    }
+    Ok(files)
+}
+
+// 删除清单 leftover 中的文件：新的文件全部写完后，这些就是新清单中没有的旧文件
+// 删除失败的文件留在 leftover 中，返回删除的数量
+pub fn remove_leftover(dir: &Path, manifest: &mut Manifest) -> usize {
+    let mut removed = 0;
+    manifest.leftover.retain(|name| {
+        if !is_plain_file_name(name) || manifest.files.iter().any(|file| file.name == *name) {
+            return false;
+        }
+        match fs::remove_file(dir.join(name)) {
+            Ok(()) => {
+                removed += 1;
+                false
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+            Err(err) => {
+                eprintln!("删除文件 {} 失败: {}", name, err);
+                true
+            }
+        }
+    });
+    removed
+}
@@ -0,0 +1,174 @@
+use crate::generate::Record;
+use crate::input::InputSet;
+use crate::split;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+
+
+// 文件名模板中的一段
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Index,              // {index}  文件序号，从1开始
+    Padded,             // {padded} 按文件总数补零的序号
+    Total,              // {total}  文件总数
+    Cidr,               // {cidr}   文件中第一条记录的来源CIDR
+    Prefix,             // {prefix} 按网段分割时文件对应的网段
+    Label,              // {label}  按标签分割时的标签值
+    LabelColumn(usize), // {label:列名} 文件中第一条记录的标签值
+    Ext,                // {ext}    输出格式对应的扩展名
+}
+
+// 分割写入时的输出目录和文件命名规则
+#[derive(Clone, Debug)]
+pub struct FileNaming {
+    dir: PathBuf,
+    parts: Vec<Part>,
+    split_label: Option<usize>,
+    prefix: Option<u8>,
+    extension: &'static str,
+}
+
+// 计算文件名序号需要的位数，file_count 为最终的文件数量
+pub fn calculate_digits(file_count: usize) -> usize {
+    file_count.to_string().len() // 计算需要的位数
+}
+
+// 把CIDR、标签值等转换成可以放进文件名的文本
+pub fn sanitize_file_part(value: &str) -> String {
+    if value.is_empty() {
+        return "none".to_string();
+    }
+    value
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl FileNaming {
+    // split_label 为按标签分割时使用的标签列，{label} 只能在这时使用
+    // prefix 为按网段分割时的前缀长度，{prefix} 只能在这时使用
+    pub fn new(
+        dir: &str,
+        template: &str,
+        input: &InputSet,
+        split_label: Option<usize>,
+        prefix: Option<u8>,
+        extension: &'static str,
+    ) -> io::Result<FileNaming> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| invalid(format!("文件名模板中的 {{ 缺少 }}：{}", template)))?;
+            let name = &rest[start + 1..end];
+            let part = match name {
+                "index" => Part::Index,
+                "padded" => Part::Padded,
+                "total" => Part::Total,
+                "cidr" => Part::Cidr,
+                "ext" => Part::Ext,
+                "prefix" if prefix.is_some() => Part::Prefix,
+                "prefix" => return Err(invalid("文件名模板中的 {prefix} 只能在按网段分割时使用".to_string())),
+                "label" if split_label.is_some() => Part::Label,
+                "label" => return Err(invalid("文件名模板中的 {label} 只能在按标签分割时使用".to_string())),
+                _ => match name.strip_prefix("label:").and_then(|label| input.label_index(label)) {
+                    Some(index) => Part::LabelColumn(index),
+                    None => {
+                        return Err(invalid(format!(
+                            "文件名模板中未知的占位符：{{{}}}（可用：index、padded、total、cidr、prefix、label、label:列名、ext）",
+                            name
+                        )))
+                    }
+                },
+            };
+            parts.push(part);
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        if template.contains('/') || template.contains('\\') {
+            return Err(invalid(format!("文件名模板不能包含路径分隔符：{}", template)));
+        }
+        Ok(FileNaming { dir: PathBuf::from(dir), parts, split_label, prefix, extension })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // 创建输出目录(已存在时不做任何事)
+    pub fn create_dir(&self) -> io::Result<()> {
+        if self.dir.as_os_str().is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)
+    }
+
+    // 单个文件的路径，例如全部写入时的 ip.txt
+    pub fn single(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    // 第 index 个文件(从1开始，共 total 个)的路径，first 为文件中的第一条记录
+    pub fn path(
+        &self,
+        index: usize,
+        total: usize,
+        first: Option<&Record>,
+        input: &InputSet,
+    ) -> PathBuf {
+        let source = first.map(|record| &input.records[record.source]);
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Index => name.push_str(&index.to_string()),
+                Part::Padded => {
+                    let digits = calculate_digits(total);
+                    name.push_str(&format!("{:0width$}", index, width = digits));
+                }
+                Part::Total => name.push_str(&total.to_string()),
+                Part::Cidr => {
+                    let cidr = source.map(|s| s.network.to_string()).unwrap_or_default();
+                    name.push_str(&sanitize_file_part(&cidr));
+                }
+                Part::Prefix => {
+                    let block = match (first, self.prefix) {
+                        (Some(record), Some(prefix)) => {
+                            let network = split::prefix_key(record.ip, prefix);
+                            format!("{}/{}", Ipv4Addr::from(network), prefix)
+                        }
+                        _ => String::new(),
+                    };
+                    name.push_str(&sanitize_file_part(&block));
+                }
+                Part::Label => {
+                    let label = match (source, self.split_label) {
+                        (Some(source), Some(column)) => input.label(source, column),
+                        _ => "",
+                    };
+                    name.push_str(&sanitize_file_part(label));
+                }
+                Part::LabelColumn(column) => {
+                    let label = source.map(|s| input.label(s, *column)).unwrap_or("");
+                    name.push_str(&sanitize_file_part(label));
+                }
+                Part::Ext => name.push_str(self.extension),
+            }
+        }
+        self.dir.join(name)
+    }
+}
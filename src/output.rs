@@ -0,0 +1,385 @@
+use crate::columnar;
+use crate::dotted;
+use crate::generate::{Record, RecordSet};
+use crate::input::InputSet;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::reverse;
+use crate::template::Template;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::ops::Range;
+use std::sync::Mutex;
+
+
+// 每条记录的输出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Plain,     // 点分十进制，每行一个
+    Csv,       // ip,cidr,标签...
+    JsonLines, // 每行一个JSON对象
+    Integer,   // 十进制u32
+    Hex,       // 8位十六进制
+    Binary,    // 大端序4字节(带端口时再加2字节端口)，没有分隔符
+    Reverse,   // 反向解析名称，例如 4.3.2.1.in-addr.arpa.
+    Sqlite,    // SQLite 数据库，每个文件一个库，记录写入 addresses 表
+    Parquet,   // Apache Parquet 列式文件，地址为无符号32位整数列
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "plain" | "txt" => Some(OutputFormat::Plain),
+            "csv" => Some(OutputFormat::Csv),
+            "jsonl" | "ndjson" | "json" => Some(OutputFormat::JsonLines),
+            "int" | "integer" | "u32" => Some(OutputFormat::Integer),
+            "hex" => Some(OutputFormat::Hex),
+            "bin" | "binary" => Some(OutputFormat::Binary),
+            "reverse" | "ptr" | "arpa" => Some(OutputFormat::Reverse),
+            "sqlite" | "sqlite3" | "db" => Some(OutputFormat::Sqlite),
+            "parquet" | "pq" => Some(OutputFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    // 分割写入时使用的文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::JsonLines => "jsonl",
+            OutputFormat::Binary => "bin",
+            OutputFormat::Sqlite => "db",
+            OutputFormat::Parquet => "parquet",
+            _ => "txt",
+        }
+    }
+}
+
+// 解析分隔符中的 \t \n \r \0 \\ 转义
+pub fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+// 记录的编码器，所有写入模式共用
+pub struct RecordFormat<'a> {
+    pub format: OutputFormat,
+    pub separator: String,   // 字段分隔符
+    pub line_ending: String, // 记录分隔符
+    pub header: bool,
+    pub ports: bool, // 记录是否带端口
+    pub template: Option<Template>, // 设置后每行按模板输出，忽略 format
+    pub row_group_size: usize,      // Parquet 每个行组的行数
+    pub threads: usize,             // 编码记录使用的线程数
+    labels: Vec<usize>,
+    cidrs: Vec<String>, // 每条输入记录的CIDR文本，只格式化一次
+    input: &'a InputSet,
+}
+
+impl<'a> RecordFormat<'a> {
+    // labels 为要附加的标签列名，找不到时返回错误
+    pub fn new(
+        format: OutputFormat,
+        input: &'a InputSet,
+        labels: &[String],
+    ) -> io::Result<RecordFormat<'a>> {
+        let labels = labels
+            .iter()
+            .map(|name| find_label(input, name))
+            .collect::<io::Result<Vec<usize>>>()?;
+        Ok(RecordFormat {
+            format,
+            separator: ",".to_string(),
+            line_ending: "\n".to_string(),
+            header: false,
+            ports: false,
+            template: None,
+            row_group_size: columnar::DEFAULT_ROW_GROUP_SIZE,
+            threads: 1,
+            labels,
+            cidrs: input.records.iter().map(|record| record.network.to_string()).collect(),
+            input,
+        })
+    }
+
+    pub fn input(&self) -> &'a InputSet {
+        self.input
+    }
+
+    // 在每个文件开头写入表头，JSON lines、二进制格式、SQLite、Parquet 和模板没有表头
+    pub fn write_header(&self, out: &mut Vec<u8>) {
+        if !self.header || self.template.is_some() {
+            return;
+        }
+        if matches!(
+            self.format,
+            OutputFormat::JsonLines | OutputFormat::Binary | OutputFormat::Sqlite | OutputFormat::Parquet
+        ) {
+            return;
+        }
+        let mut columns = self.fixed_columns();
+        for &label in &self.labels {
+            columns.push(&self.input.label_names[label]);
+        }
+        for (index, column) in columns.iter().enumerate() {
+            if index > 0 {
+                out.extend_from_slice(self.separator.as_bytes());
+            }
+            self.push_field(out, column);
+        }
+        out.extend_from_slice(self.line_ending.as_bytes());
+    }
+
+    // 标签之前的固定列：表头中的列名，JSON lines 中为对象的键
+    fn fixed_columns(&self) -> Vec<&str> {
+        let mut columns = vec![if self.format == OutputFormat::Reverse { "ptr" } else { "ip" }];
+        // 纯文本格式的端口写成 ip:port，不单独占一列
+        if self.ports && self.format != OutputFormat::Plain {
+            columns.push("port");
+        }
+        if matches!(self.format, OutputFormat::Csv | OutputFormat::JsonLines) {
+            columns.push("cidr");
+        }
+        columns
+    }
+
+    // 附加的标签不能与固定列或其他标签重名，否则表头中的列名或 JSON 对象的键会重复
+    // 没有端口时也保留 port，校验时会把这个键或列当作端口读取
+    // SQLite 和 Parquet 由各自的 check_columns 检查；使用模板时不输出标签列
+    pub fn check_columns(&self) -> io::Result<()> {
+        let has_names = match self.format {
+            OutputFormat::JsonLines => true,
+            OutputFormat::Binary | OutputFormat::Sqlite | OutputFormat::Parquet => false,
+            _ => self.header,
+        };
+        if !has_names || self.template.is_some() {
+            return Ok(());
+        }
+        let mut names = self.fixed_columns();
+        if !names.contains(&"port") {
+            names.push("port");
+        }
+        for &label in &self.labels {
+            let name = self.input.label_names[label].as_str();
+            if names.contains(&name) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("标签列 {} 与输出中的列名重复，请不要用 --with-labels 附加这一列", name),
+                ));
+            }
+            names.push(name);
+        }
+        Ok(())
+    }
+
+    // 把一条记录追加到缓冲区
+    pub fn encode(&self, record: &Record, out: &mut Vec<u8>) {
+        if let Some(template) = &self.template {
+            template.render(record, self.input, out);
+            out.extend_from_slice(self.line_ending.as_bytes());
+            return;
+        }
+        let source = &self.input.records[record.source];
+        let value = record.ip;
+        match self.format {
+            OutputFormat::Binary => {
+                out.extend_from_slice(&value.to_be_bytes());
+                if let Some(port) = record.port {
+                    out.extend_from_slice(&port.to_be_bytes());
+                }
+                return;
+            }
+            OutputFormat::JsonLines => {
+                // 地址和CIDR中没有需要转义的字符
+                out.extend_from_slice(b"{\"ip\":\"");
+                dotted::push_ipv4(out, value);
+                out.push(b'"');
+                if let Some(port) = record.port {
+                    out.extend_from_slice(b",\"port\":");
+                    dotted::push_decimal(out, port as u64);
+                }
+                out.extend_from_slice(b",\"cidr\":\"");
+                out.extend_from_slice(self.cidrs[record.source].as_bytes());
+                out.push(b'"');
+                for &label in &self.labels {
+                    out.push(b',');
+                    push_json_string(out, &self.input.label_names[label]);
+                    out.push(b':');
+                    push_json_string(out, self.input.label(source, label));
+                }
+                out.push(b'}');
+            }
+            OutputFormat::Reverse => reverse::push_reverse_name(record.ip, out),
+            // SQLite 和 Parquet 不经过编码器写入，这里只在估算大小时用到，按纯文本处理
+            OutputFormat::Plain | OutputFormat::Sqlite | OutputFormat::Parquet => {
+                dotted::push_ipv4(out, value);
+                if let Some(port) = record.port {
+                    out.push(b':');
+                    dotted::push_decimal(out, port as u64);
+                }
+            }
+            OutputFormat::Integer | OutputFormat::Hex | OutputFormat::Csv => {
+                match self.format {
+                    OutputFormat::Integer => dotted::push_decimal(out, value as u64),
+                    OutputFormat::Hex => dotted::push_hex(out, value),
+                    _ => dotted::push_ipv4(out, value),
+                }
+                if let Some(port) = record.port {
+                    out.extend_from_slice(self.separator.as_bytes());
+                    dotted::push_decimal(out, port as u64);
+                }
+                if self.format == OutputFormat::Csv {
+                    out.extend_from_slice(self.separator.as_bytes());
+                    self.push_field(out, &self.cidrs[record.source]);
+                }
+            }
+        }
+        if self.format != OutputFormat::JsonLines {
+            for &label in &self.labels {
+                out.extend_from_slice(self.separator.as_bytes());
+                self.push_field(out, self.input.label(source, label));
+            }
+        }
+        out.extend_from_slice(self.line_ending.as_bytes());
+    }
+
+    // CSV格式下，包含分隔符、引号或换行的字段用双引号包裹
+    fn push_field(&self, out: &mut Vec<u8>, field: &str) {
+        let needs_quotes = self.format == OutputFormat::Csv
+            && (field.contains(self.separator.as_str())
+                || field.contains('"')
+                || field.contains('\n')
+                || field.contains('\r'));
+        if needs_quotes {
+            out.push(b'"');
+            out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field.as_bytes());
+        }
+    }
+}
+
+fn push_json_string(out: &mut Vec<u8>, text: &str) {
+    // 写入 Vec 不会失败
+    serde_json::to_writer(&mut *out, text).expect("写入JSON字符串失败");
+}
+
+// 查找标签列的下标
+pub fn find_label(input: &InputSet, name: &str) -> io::Result<usize> {
+    input.label_index(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("输入中没有标签列：{}（可用的列：{}）", name, input.label_names.join(",")),
+        )
+    })
+}
+
+// 写入一个文件的记录统计
+#[derive(Clone, Debug, Default)]
+pub struct RecordStats {
+    pub records: u64,
+    pub header: bool,
+    pub first: Option<Ipv4Addr>,
+    pub last: Option<Ipv4Addr>,
+    pub sources: Vec<usize>, // 涉及的输入记录下标，按首次出现的顺序
+}
+
+impl RecordStats {
+    pub fn add(&mut self, record: &Record) {
+        self.records += 1;
+        let ip = Ipv4Addr::from(record.ip);
+        if self.first.is_none() {
+            self.first = Some(ip);
+        }
+        self.last = Some(ip);
+        // 记录通常按来源连续排列，只有来源变化时才需要查重
+        if self.sources.last() != Some(&record.source) && !self.sources.contains(&record.source) {
+            self.sources.push(record.source);
+        }
+    }
+
+    // 合并下一段记录的统计，结果与逐条调用 add 相同
+    pub fn merge(&mut self, next: RecordStats) {
+        self.records += next.records;
+        self.first = self.first.or(next.first);
+        self.last = next.last.or(self.last);
+        for source in next.sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+    }
+}
+
+// 按格式把 ips 中 ranges 范围内的记录依次写入 out，header 为 true 时开头附带表头(追加到已有文件时不需要)
+// 记录按 CHUNK_SIZE 条分块，每块在 format.threads 个线程中的一个里生成、编码并统计，再按块的顺序写出，
+// 所以输出与单线程完全相同；每写出一块更新一次写入进度
+pub fn write_records<W: Write>(
+    out: &mut W,
+    ips: &RecordSet,
+    ranges: &[Range<usize>],
+    format: &RecordFormat,
+    header: bool,
+    progress: Option<&Progress>,
+) -> io::Result<RecordStats> {
+    let mut stats = RecordStats::default();
+    let mut buffer = Vec::new();
+    if header {
+        format.write_header(&mut buffer);
+    }
+    stats.header = !buffer.is_empty();
+    out.write_all(&buffer)?;
+
+    let chunks: Vec<Range<usize>> = ranges
+        .iter()
+        .flat_map(|range| {
+            let end = range.end;
+            range.clone().step_by(parallel::CHUNK_SIZE).map(move |start| start..(start + parallel::CHUNK_SIZE).min(end))
+        })
+        .collect();
+    // 写出后的缓冲区放回这里，供后面的块重复使用
+    let buffers = Mutex::new(vec![buffer]);
+    parallel::ordered(
+        chunks.len(),
+        format.threads,
+        |chunk| {
+            let mut buffer = buffers.lock().expect("缓冲区的锁已损坏").pop().unwrap_or_default();
+            buffer.clear();
+            let mut chunk_stats = RecordStats::default();
+            for record in ips.range(chunks[chunk].start, chunks[chunk].end) {
+                format.encode(&record, &mut buffer);
+                chunk_stats.add(&record);
+            }
+            (buffer, chunk_stats)
+        },
+        |(buffer, chunk_stats)| {
+            out.write_all(&buffer)?;
+            if let Some(progress) = progress {
+                progress.add_written(chunk_stats.records);
+            }
+            stats.merge(chunk_stats);
+            buffers.lock().expect("缓冲区的锁已损坏").push(buffer);
+            Ok::<(), io::Error>(())
+        },
+    )?;
+    out.flush()?;
+    Ok(stats)
+}
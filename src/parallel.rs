@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+
+
+// 生成地址和编码记录时每个任务处理的数量，任务之间互不依赖，结果按任务顺序拼接
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+// 默认的线程数：可用的CPU核心数
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// 用 threads 个线程处理 items 中的每一项，空闲的线程按顺序领取下一项
+// 每一项自己带着结果的位置(例如输出切片)，所以处理顺序不影响结果
+pub fn for_each<T: Send, F: Fn(T) + Sync>(items: Vec<T>, threads: usize, f: F) {
+    if threads <= 1 || items.len() <= 1 {
+        items.into_iter().for_each(f);
+        return;
+    }
+    let workers = threads.min(items.len());
+    let queue = Mutex::new(items.into_iter());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let item = queue.lock().expect("任务队列的锁已损坏").next();
+                match item {
+                    Some(item) => f(item),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+// 按序号顺序处理 0..count 的结果：threads 个线程在整个过程中一直运行，依次领取序号并调用 produce，
+// 调用方在当前线程中按序号从小到大把结果交给 consume，所以输出顺序与单线程相同
+// 领先于 consume 的结果最多 threads * 2 个，内存占用不随 count 增长
+// consume 返回错误时其他线程不再领取新的序号，返回这个错误
+pub fn ordered<T, E, P, C>(count: usize, threads: usize, produce: P, mut consume: C) -> Result<(), E>
+where
+    T: Send,
+    P: Fn(usize) -> T + Sync,
+    C: FnMut(T) -> Result<(), E>,
+{
+    if threads <= 1 || count <= 1 {
+        return (0..count).try_for_each(|index| consume(produce(index)));
+    }
+    let window = threads * 2;
+    let state = Mutex::new(Window { next: 0, consumed: 0, stopped: false });
+    let changed = Condvar::new();
+    let (sender, receiver) = mpsc::channel::<(usize, T)>();
+    thread::scope(|scope| {
+        for _ in 0..threads.min(count) {
+            let sender = sender.clone();
+            let (state, changed, produce) = (&state, &changed, &produce);
+            scope.spawn(move || {
+                let _stop = StopOnPanic { state, changed };
+                loop {
+                    let index = {
+                        let mut window_state = state.lock().expect("任务窗口的锁已损坏");
+                        while !window_state.stopped
+                            && window_state.next < count
+                            && window_state.next >= window_state.consumed + window
+                        {
+                            window_state = changed.wait(window_state).expect("任务窗口的锁已损坏");
+                        }
+                        if window_state.stopped || window_state.next >= count {
+                            break;
+                        }
+                        window_state.next += 1;
+                        window_state.next - 1
+                    };
+                    if sender.send((index, produce(index))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // 先完成的结果暂存起来，等前面的序号都交给 consume 之后再处理
+        let mut pending = BTreeMap::new();
+        let mut result = Ok(());
+        let mut consumed = 0;
+        while consumed < count {
+            let Ok((index, value)) = receiver.recv() else {
+                break; // 有线程 panic，scope 结束时会继续传播
+            };
+            pending.insert(index, value);
+            while let Some(value) = pending.remove(&consumed) {
+                result = consume(value);
+                consumed += 1;
+                let mut window_state = state.lock().expect("任务窗口的锁已损坏");
+                window_state.consumed = consumed;
+                window_state.stopped |= result.is_err();
+                changed.notify_all();
+                if result.is_err() {
+                    break;
+                }
+            }
+            if result.is_err() {
+                break;
+            }
+        }
+        result
+    })
+}
+
+// ordered 中已领取和已处理到的序号
+struct Window {
+    next: usize,
+    consumed: usize,
+    stopped: bool,
+}
+
+// 线程 panic 时通知其他线程停止，避免它们一直等待永远不会处理的序号
+struct StopOnPanic<'a> {
+    state: &'a Mutex<Window>,
+    changed: &'a Condvar,
+}
+
+impl Drop for StopOnPanic<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            if let Ok(mut state) = self.state.lock() {
+                state.stopped = true;
+            }
+            self.changed.notify_all();
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// 解析端口列表，例如 443,2053,2083-2087,8443
+// 保持书写顺序，重复的端口只保留第一次出现的位置
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports: Vec<u16> = Vec::new();
+    let mut seen = vec![false; 65536];
+
+    for part in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+            None => {
+                let port = parse_port(part)?;
+                (port, port)
+            }
+        };
+        if start > end {
+            return Err(format!("端口范围 {} 的起始端口大于结束端口", part));
+        }
+        for port in start..=end {
+            if !seen[port as usize] {
+                seen[port as usize] = true;
+                ports.push(port);
+            }
+        }
+    }
+    if ports.is_empty() {
+        return Err(format!("端口列表为空：{}", spec));
+    }
+    Ok(ports)
+}
+
+// 把端口列表写成 80,443,8000-8010 的形式：排序后连续的端口合并成范围
+pub fn format_ports(ports: &[u16]) -> String {
+    let mut sorted = ports.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut parts: Vec<String> = Vec::new();
+    let mut index = 0;
+    while index < sorted.len() {
+        let start = sorted[index];
+        let mut end = start;
+        while index + 1 < sorted.len() && sorted[index + 1] == end + 1 {
+            index += 1;
+            end = sorted[index];
+        }
+        parts.push(if start == end { start.to_string() } else { format!("{}-{}", start, end) });
+        index += 1;
+    }
+    parts.join(",")
+}
+
+fn parse_port(text: &str) -> Result<u16, String> {
+    match text.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("无效的端口：{}（有效范围 1-65535）", text.trim())),
+    }
+}
@@ -0,0 +1,231 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+
+// 标准错误是终端时刷新进度行的间隔
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+// 标准错误不是终端(重定向到文件或日志)时，每隔这么久输出一行进度
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+// 进度行的显示状态
+struct Display {
+    phase_start: Instant,
+    last_draw: Instant,
+    width: usize, // 终端中上一次进度行的显示宽度，清除时用空格覆盖
+}
+
+// 生成和写入的进度，显示在标准错误中
+// 计数可以在多个线程中同时更新；到了刷新间隔时，由正在更新计数的线程顺便输出一次
+pub struct Progress {
+    enabled: bool,
+    terminal: bool,
+    generated: AtomicU64,
+    generate_total: AtomicU64,
+    written: AtomicU64,
+    write_total: AtomicU64,
+    files: AtomicU64,
+    files_total: AtomicU64,
+    display: Mutex<Display>,
+}
+
+impl Progress {
+    // enabled 为 false 时(--no-progress)只计数，不输出任何内容
+    pub fn new(enabled: bool) -> Progress {
+        let now = Instant::now();
+        Progress {
+            enabled,
+            terminal: io::stderr().is_terminal(),
+            generated: AtomicU64::new(0),
+            generate_total: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+            write_total: AtomicU64::new(0),
+            files: AtomicU64::new(0),
+            files_total: AtomicU64::new(0),
+            display: Mutex::new(Display { phase_start: now, last_draw: now, width: 0 }),
+        }
+    }
+
+    // 开始生成，total 为要生成的地址总数
+    pub fn start_generate(&self, total: u64) {
+        self.generated.store(0, Ordering::Relaxed);
+        self.generate_total.store(total, Ordering::Relaxed);
+        self.restart();
+    }
+
+    pub fn add_generated(&self, count: u64) {
+        self.generated.fetch_add(count, Ordering::Relaxed);
+        self.tick();
+    }
+
+    // 开始写入，records 为要写入的记录总数，files 为文件数
+    pub fn start_write(&self, records: u64, files: u64) {
+        self.written.store(0, Ordering::Relaxed);
+        self.write_total.store(records, Ordering::Relaxed);
+        self.files.store(0, Ordering::Relaxed);
+        self.files_total.store(files, Ordering::Relaxed);
+        self.restart();
+    }
+
+    pub fn add_written(&self, count: u64) {
+        self.written.fetch_add(count, Ordering::Relaxed);
+        self.tick();
+    }
+
+    pub fn file_done(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.tick();
+    }
+
+    // 统计经过 records 的记录数，每 64K 条更新一次写入进度
+    pub fn track<I: Iterator>(&self, records: I) -> Tracked<'_, I> {
+        Tracked { progress: self, records, pending: 0 }
+    }
+
+    // 清除终端中的进度行，之后可以正常输出其他信息；下一次刷新时会重新显示
+    pub fn clear(&self) {
+        let mut display = self.display.lock().expect("进度显示的锁已损坏");
+        if display.width > 0 {
+            eprint!("\r{}\r", " ".repeat(display.width));
+            display.width = 0;
+        }
+    }
+
+    fn restart(&self) {
+        self.clear();
+        let now = Instant::now();
+        let mut display = self.display.lock().expect("进度显示的锁已损坏");
+        display.phase_start = now;
+        display.last_draw = now;
+    }
+
+    fn tick(&self) {
+        if !self.enabled {
+            return;
+        }
+        // 其他线程正在输出时跳过这一次
+        let Ok(mut display) = self.display.try_lock() else {
+            return;
+        };
+        let interval = if self.terminal { REDRAW_INTERVAL } else { LOG_INTERVAL };
+        if display.last_draw.elapsed() < interval {
+            return;
+        }
+        display.last_draw = Instant::now();
+        let line = self.describe(display.phase_start.elapsed());
+        if self.terminal {
+            let width = display_width(&line);
+            let padding = display.width.saturating_sub(width);
+            eprint!("\r{}{}", line, " ".repeat(padding));
+            display.width = width;
+        } else {
+            eprintln!("进度：{}", line);
+        }
+        let _ = io::stderr().flush();
+    }
+
+    // 当前阶段的进度：已完成的数量、速度和预计剩余时间
+    fn describe(&self, elapsed: Duration) -> String {
+        let generated = self.generated.load(Ordering::Relaxed);
+        let write_total = self.write_total.load(Ordering::Relaxed);
+        if write_total == 0 {
+            let total = self.generate_total.load(Ordering::Relaxed);
+            return format!(
+                "已生成 {}/{} 个地址 ({})，{} 个/秒，预计剩余 {}",
+                generated,
+                total,
+                percent(generated, total),
+                format_rate(generated, elapsed),
+                format_eta(generated, total, elapsed)
+            );
+        }
+        let written = self.written.load(Ordering::Relaxed);
+        format!(
+            "已生成 {} 个地址，已写入 {}/{} 条记录 ({})，文件 {}/{}，{} 条/秒，预计剩余 {}",
+            generated,
+            written,
+            write_total,
+            percent(written, write_total),
+            self.files.load(Ordering::Relaxed),
+            self.files_total.load(Ordering::Relaxed),
+            format_rate(written, elapsed),
+            format_eta(written, write_total, elapsed)
+        )
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// Progress::track 返回的迭代器，先在本地计数，攒够一批再更新共享的计数
+pub struct Tracked<'a, I> {
+    progress: &'a Progress,
+    records: I,
+    pending: u64,
+}
+
+impl<I: Iterator> Iterator for Tracked<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.records.next();
+        if item.is_some() {
+            self.pending += 1;
+            if self.pending == 64 * 1024 {
+                self.progress.add_written(self.pending);
+                self.pending = 0;
+            }
+        }
+        item
+    }
+}
+
+impl<I> Drop for Tracked<'_, I> {
+    fn drop(&mut self) {
+        if self.pending > 0 {
+            self.progress.written.fetch_add(self.pending, Ordering::Relaxed);
+        }
+    }
+}
+
+// 终端中的显示宽度：中文等非ASCII字符按两列计算
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn percent(done: u64, total: u64) -> String {
+    if total == 0 {
+        return "100.0%".to_string();
+    }
+    format!("{:.1}%", done as f64 * 100.0 / total as f64)
+}
+
+// 每秒处理的数量，例如 12.3M
+fn format_rate(done: u64, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return "-".to_string();
+    }
+    let rate = done as f64 / seconds;
+    if rate >= 1e6 {
+        format!("{:.1}M", rate / 1e6)
+    } else if rate >= 1e3 {
+        format!("{:.1}K", rate / 1e3)
+    } else {
+        format!("{:.0}", rate)
+    }
+}
+
+// 按目前的平均速度估算剩余时间，格式为 时:分:秒
+fn format_eta(done: u64, total: u64, elapsed: Duration) -> String {
+    if done == 0 {
+        return "--:--:--".to_string();
+    }
+    let remaining = total.saturating_sub(done) as f64 * elapsed.as_secs_f64() / done as f64;
+    let seconds = remaining.round() as u64;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
@@ -0,0 +1,132 @@
+use ipnetwork::Ipv4Network;
+use std::net::Ipv4Addr;
+
+
+// IPv4地址区间，两端都包含：(起始地址, 结束地址)
+pub type Range4 = (u32, u32);
+
+// 网络地址对应的区间
+pub fn network_range(network: Ipv4Network) -> Range4 {
+    (u32::from(network.network()), u32::from(network.broadcast()))
+}
+
+// 排序并合并重叠或相邻的区间
+pub fn merge(ranges: Vec<Range4>) -> Vec<Range4> {
+    let wide = ranges.into_iter().map(|(start, end)| (start as u128, end as u128)).collect();
+    merge_wide(wide)
+        .into_iter()
+        .map(|(start, end)| (start as u32, end as u32))
+        .collect()
+}
+
+// 与 merge 相同，区间用u128表示，IPv4和IPv6都可以使用
+pub fn merge_wide(mut ranges: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if last.1 == u128::MAX || start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+// 把区间拆成数量最少的CIDR，返回 (网络地址, 前缀长度)；bits 为地址的位数，IPv4为32，IPv6为128
+pub fn split_cidrs(start: u128, end: u128, bits: u8) -> Vec<(u128, u8)> {
+    let mut cidrs = Vec::new();
+    let mut start = start;
+    loop {
+        // 从 start 开始、不超过 end 的最大对齐块，size 为块内地址的位数
+        let mut size = start.trailing_zeros().min(bits as u32);
+        let last = loop {
+            let mask = if size >= 128 { u128::MAX } else { (1u128 << size) - 1 };
+            if start | mask <= end {
+                break start | mask;
+            }
+            size -= 1;
+        };
+        cidrs.push((start, bits - size as u8));
+        if last >= end {
+            return cidrs;
+        }
+        start = last + 1;
+    }
+}
+
+// 把一个区间加入已排序、已合并的区间列表，保持列表有序且不重叠
+pub fn insert(ranges: &mut Vec<Range4>, range: Range4) {
+    // 与 range 重叠或相邻的区间是连续的一段 [first, last)
+    let first = ranges.partition_point(|&(_, end)| (end as u64) + 1 < range.0 as u64);
+    let last = ranges.partition_point(|&(start, _)| start as u64 <= range.1 as u64 + 1);
+    if first == last {
+        ranges.insert(first, range);
+        return;
+    }
+    let start = range.0.min(ranges[first].0);
+    let end = range.1.max(ranges[last - 1].1);
+    ranges.splice(first..last, [(start, end)]);
+}
+
+// range 中没有被 covered(已排序、已合并)覆盖的部分
+pub fn subtract(range: Range4, covered: &[Range4]) -> Vec<Range4> {
+    let mut rest = Vec::new();
+    let mut next = range.0 as u64; // 还没有处理的第一个地址
+    let first = covered.partition_point(|&(_, end)| end < range.0);
+    for &(start, end) in &covered[first..] {
+        if start > range.1 {
+            break;
+        }
+        if (start as u64) > next {
+            rest.push((next as u32, start - 1));
+        }
+        next = next.max(end as u64 + 1);
+    }
+    if next <= range.1 as u64 {
+        rest.push((next as u32, range.1));
+    }
+    rest
+}
+
+// ranges 中去掉 excluded 之后剩下的部分；两者都已排序、已合并
+pub fn difference(ranges: &[Range4], excluded: &[Range4]) -> Vec<Range4> {
+    ranges.iter().flat_map(|&range| subtract(range, excluded)).collect()
+}
+
+// 两组区间的交集；两者都已排序、已合并
+pub fn intersection(a: &[Range4], b: &[Range4]) -> Vec<Range4> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start <= end {
+            result.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+// 地址是否落在 ranges(已排序、已合并)中
+pub fn contains(ranges: &[Range4], ip: u32) -> bool {
+    let index = ranges.partition_point(|&(_, end)| end < ip);
+    ranges.get(index).is_some_and(|&(start, _)| start <= ip)
+}
+
+// 把区间拆成数量最少的CIDR，按地址排序
+pub fn to_networks(ranges: &[Range4]) -> Vec<Ipv4Network> {
+    let mut networks = Vec::new();
+    for &(start, end) in ranges {
+        for (network, prefix) in split_cidrs(start as u128, end as u128, 32) {
+            let network = Ipv4Network::new(Ipv4Addr::from(network as u32), prefix)
+                .expect("拆分得到的前缀长度不会超过32");
+            networks.push(network);
+        }
+    }
+    networks
+}
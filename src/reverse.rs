@@ -0,0 +1,101 @@
+use crate::dotted;
+use crate::ranges;
+use ipnetwork::IpNetwork;
+use std::net::Ipv4Addr;
+
+
+// 把IPv4地址的反向解析名称追加到缓冲区，例如 1.2.3.4 -> 4.3.2.1.in-addr.arpa.
+pub fn push_reverse_name(ip: u32, out: &mut Vec<u8>) {
+    for octet in ip.to_le_bytes() {
+        dotted::push_octet(out, octet);
+        out.push(b'.');
+    }
+    out.extend_from_slice(b"in-addr.arpa.");
+}
+
+// 从反向解析名称中解析出IPv4地址，末尾的点可以省略
+pub fn parse_reverse_name(text: &str) -> Option<Ipv4Addr> {
+    let text = text.trim().trim_end_matches('.');
+    let octets = text.strip_suffix(".in-addr.arpa")?;
+    let mut parts = octets.split('.').map(|part| part.parse::<u8>());
+    let mut ip = [0u8; 4];
+    for octet in ip.iter_mut().rev() {
+        *octet = parts.next()?.ok()?;
+    }
+    match parts.next() {
+        Some(_) => None,
+        None => Some(Ipv4Addr::from(ip)),
+    }
+}
+
+// IPv4的反向区：按8位对齐，前缀长于 /24 时使用 RFC 2317 的无类别委派名称，例如 0/26.2.0.192.in-addr.arpa.
+fn v4_zones(network: u32, prefix: u8, zones: &mut Vec<String>) {
+    if prefix > 24 {
+        let [a, b, c, d] = network.to_be_bytes();
+        zones.push(format!("{}/{}.{}.{}.{}.in-addr.arpa.", d, prefix, c, b, a));
+        return;
+    }
+    let boundary = prefix.div_ceil(8) * 8;
+    let octets = (boundary / 8) as usize;
+    for index in 0..1u32 << (boundary - prefix) {
+        let zone = if boundary == 0 { network } else { network + (index << (32 - boundary)) };
+        let mut name = String::new();
+        for octet in zone.to_be_bytes()[..octets].iter().rev() {
+            name.push_str(&octet.to_string());
+            name.push('.');
+        }
+        name.push_str("in-addr.arpa.");
+        zones.push(name);
+    }
+}
+
+// IPv6的反向区：按4位(一个十六进制数字)对齐
+fn v6_zones(network: u128, prefix: u8, zones: &mut Vec<String>) {
+    let boundary = prefix.div_ceil(4) * 4;
+    let nibbles = (boundary / 4) as usize;
+    for index in 0..1u128 << (boundary - prefix) {
+        let zone = if boundary == 0 { network } else { network + (index << (128 - boundary as u32)) };
+        let digits = format!("{:032x}", zone);
+        let mut name = String::new();
+        for digit in digits[..nibbles].chars().rev() {
+            name.push(digit);
+            name.push('.');
+        }
+        name.push_str("ip6.arpa.");
+        zones.push(name);
+    }
+}
+
+// 覆盖全部输入所需的最少的反向区：先合并重叠和相邻的网络，再按区的边界拆分
+// IPv4的区在前，IPv6的区在后，各自按地址排序
+pub fn reverse_zones(networks: &[IpNetwork]) -> Vec<String> {
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for network in networks {
+        match network {
+            IpNetwork::V4(network) => {
+                let (start, end) = ranges::network_range(*network);
+                v4.push((start as u128, end as u128));
+            }
+            IpNetwork::V6(network) => {
+                let start = u128::from(network.network());
+                let size = 128 - network.prefix() as u32;
+                let mask = if size >= 128 { u128::MAX } else { (1u128 << size) - 1 };
+                v6.push((start, start | mask));
+            }
+        }
+    }
+
+    let mut zones = Vec::new();
+    for (start, end) in ranges::merge_wide(v4) {
+        for (network, prefix) in ranges::split_cidrs(start, end, 32) {
+            v4_zones(network as u32, prefix, &mut zones);
+        }
+    }
+    for (start, end) in ranges::merge_wide(v6) {
+        for (network, prefix) in ranges::split_cidrs(start, end, 128) {
+            v6_zones(network, prefix, &mut zones);
+        }
+    }
+    zones
+}
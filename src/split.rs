@@ -0,0 +1,275 @@
+use crate::cli::SplitMode;
+use crate::generate::{GeneratedIp, RecordSet};
+use crate::output::{self, RecordFormat};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+use std::ops::Range;
+
+
+// 一个输出文件包含的记录：按写入顺序排列的若干段记录序号
+pub type FilePlan = Vec<Range<usize>>;
+
+// 把一段记录追加到文件中，和上一段相连时合并
+fn push_range(plan: &mut FilePlan, range: Range<usize>) {
+    if range.is_empty() {
+        return;
+    }
+    match plan.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => plan.push(range),
+    }
+}
+
+// 文件中的记录数
+pub fn plan_len(plan: &FilePlan) -> usize {
+    plan.iter().map(|range| range.len()).sum()
+}
+
+// 等份分割：余数分给前面的文件，各文件的记录数最多相差1条
+// 份数多于记录数时只分成 total 份，不产生空文件
+pub fn plan_equal(total: usize, num_segments: usize) -> Vec<FilePlan> {
+    let num_segments = num_segments.min(total);
+    if num_segments == 0 {
+        return Vec::new();
+    }
+    let ips_per_segment = total / num_segments;
+    let remainder = total % num_segments;
+    (0..num_segments)
+        .map(|segment| {
+            let start = segment * ips_per_segment + segment.min(remainder);
+            let end = start + ips_per_segment + usize::from(segment < remainder);
+            let mut plan = FilePlan::new();
+            push_range(&mut plan, start..end);
+            plan
+        })
+        .collect()
+}
+
+// 每个文件最多 max_lines_per_file 条记录
+pub fn plan_max_lines(total: usize, max_lines_per_file: usize) -> Vec<FilePlan> {
+    (0..total)
+        .step_by(max_lines_per_file)
+        .map(|start| {
+            let mut plan = FilePlan::new();
+            push_range(&mut plan, start..(start + max_lines_per_file).min(total));
+            plan
+        })
+        .collect()
+}
+
+// 每个文件最多 max_bytes 字节(含表头)，按记录编码后的实际长度计算，
+// 下一条记录会超过上限时换一个新文件；返回计划和单条记录就超过上限的文件数
+pub fn plan_max_bytes(ips: &RecordSet, format: &RecordFormat, max_bytes: u64) -> (Vec<FilePlan>, usize) {
+    let mut buffer = Vec::new();
+    format.write_header(&mut buffer);
+    let header_len = buffer.len() as u64;
+
+    let mut plans: Vec<FilePlan> = Vec::new();
+    let mut current: FilePlan = Vec::new();
+    let mut current_len = header_len;
+    let mut oversized = 0;
+    for (index, record) in ips.iter().enumerate() {
+        buffer.clear();
+        format.encode(&record, &mut buffer);
+        let record_len = buffer.len() as u64;
+        if !current.is_empty() && current_len + record_len > max_bytes {
+            plans.push(std::mem::take(&mut current));
+            current_len = header_len;
+        }
+        if current.is_empty() && current_len + record_len > max_bytes {
+            oversized += 1;
+        }
+        push_range(&mut current, index..index + 1);
+        current_len += record_len;
+    }
+    if !current.is_empty() {
+        plans.push(current);
+    }
+    (plans, oversized)
+}
+
+// 按IP地址分组，每组一个文件，文件按组首次出现的顺序排列
+pub fn plan_groups<K, F>(ips: &RecordSet, key: F) -> Vec<FilePlan>
+where
+    K: Clone + Hash + Eq,
+    F: Fn(&GeneratedIp) -> K,
+{
+    let per_ip = ips.per_ip();
+    let mut plans: Vec<FilePlan> = Vec::new();
+    let mut groups: HashMap<K, usize> = HashMap::new();
+    let mut current: Option<(K, usize)> = None; // 上一个IP地址所在的组，连续的地址不必查表
+
+    for (position, generated) in ips.ips().iter().enumerate() {
+        let group_key = key(generated);
+        let group = match &current {
+            Some((last_key, group)) if *last_key == group_key => *group,
+            _ => {
+                let next = plans.len();
+                let group = *groups.entry(group_key.clone()).or_insert(next);
+                if group == next {
+                    plans.push(Vec::new());
+                }
+                current = Some((group_key, group));
+                group
+            }
+        };
+        push_range(&mut plans[group], position * per_ip..(position + 1) * per_ip);
+    }
+    plans
+}
+
+// 属于同一个 /prefix 网段的IP地址
+pub fn prefix_key(ip: u32, prefix: u8) -> u32 {
+    if prefix == 0 {
+        0
+    } else {
+        ip & (u32::MAX << (32 - prefix as u32))
+    }
+}
+
+// 每个文件最多 max_lines_per_file 条记录，但不会把一个 /prefix 网段拆到两个文件中
+// 同一网段的地址不连续时也归入同一组，各组按首次出现的顺序装入文件
+// 单个网段超过上限时，这个网段单独占一个文件；返回计划和超过上限的文件数
+pub fn plan_whole_blocks(
+    ips: &RecordSet,
+    max_lines_per_file: usize,
+    prefix: u8,
+) -> (Vec<FilePlan>, usize) {
+    let mut plans: Vec<FilePlan> = Vec::new();
+    let mut current: FilePlan = Vec::new();
+    let mut current_len = 0;
+    let mut oversized = 0;
+
+    for block in plan_groups(ips, |generated| prefix_key(generated.ip, prefix)) {
+        let block_len = plan_len(&block);
+        if !current.is_empty() && current_len + block_len > max_lines_per_file {
+            plans.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if block_len > max_lines_per_file {
+            oversized += 1;
+        }
+        for range in block {
+            push_range(&mut current, range);
+        }
+        current_len += block_len;
+    }
+    if !current.is_empty() {
+        plans.push(current);
+    }
+    (plans, oversized)
+}
+
+// 按分割模式计算每个文件包含的记录
+// 第二个值为超过上限、只能单独写入一个文件的记录数(bytes:)或网段数(lines:N/P)，其他模式为0
+pub fn plan_files(
+    ips: &RecordSet,
+    format: &RecordFormat,
+    mode: &SplitMode,
+) -> io::Result<(Vec<FilePlan>, usize)> {
+    let input = format.input();
+    let plans = match mode {
+        SplitMode::All => (vec![vec![0..ips.len()]], 0),
+        SplitMode::Equal(num_segments) => (plan_equal(ips.len(), *num_segments), 0),
+        SplitMode::MaxLines(max_lines) => (plan_max_lines(ips.len(), *max_lines), 0),
+        SplitMode::MaxBytes(max_bytes) => plan_max_bytes(ips, format, *max_bytes),
+        SplitMode::Label(name) => {
+            let label = output::find_label(input, name)?;
+            (plan_groups(ips, |generated| input.label(&input.records[generated.source], label)), 0)
+        }
+        SplitMode::Cidr => (plan_groups(ips, |generated| input.records[generated.source].network), 0),
+        SplitMode::Prefix(prefix) => (plan_groups(ips, |generated| prefix_key(generated.ip, *prefix)), 0),
+        SplitMode::WholeBlocks { max_lines, prefix } => plan_whole_blocks(ips, *max_lines, *prefix),
+    };
+    Ok(plans)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input;
+    use crate::output::OutputFormat;
+    use std::net::Ipv4Addr;
+
+    // 把 network/prefix 中的全部地址依次加入 ips
+    fn push_network(ips: &mut Vec<GeneratedIp>, network: &str, prefix: u32) {
+        let start = u32::from(network.parse::<Ipv4Addr>().unwrap());
+        for offset in 0..1u32 << (32 - prefix) {
+            ips.push(GeneratedIp { ip: start + offset, source: 0 });
+        }
+    }
+
+    #[test]
+    fn equal_sizes_differ_by_at_most_one() {
+        for (total, segments) in [(10, 3), (100, 7), (65536, 10), (5, 5)] {
+            let plans = plan_equal(total, segments);
+            assert_eq!(plans.len(), segments);
+            let sizes: Vec<usize> = plans.iter().map(plan_len).collect();
+            assert_eq!(sizes.iter().sum::<usize>(), total);
+            assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1, "{:?}", sizes);
+            // 各文件首尾相接，覆盖全部记录
+            let ranges: Vec<Range<usize>> = plans.into_iter().flatten().collect();
+            assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+        }
+    }
+
+    #[test]
+    fn equal_with_more_segments_than_records() {
+        let plans = plan_equal(3, 10);
+        assert_eq!(plans, vec![vec![0..1], vec![1..2], vec![2..3]]);
+        assert!(plan_equal(0, 4).is_empty());
+    }
+
+    #[test]
+    fn max_bytes_counts_header_and_oversized_records() {
+        let input = input::from_cidrs(&["10.0.0.0/29".to_string()]).unwrap();
+        let mut ips = Vec::new();
+        push_network(&mut ips, "10.0.0.0", 29); // 每条记录 "10.0.0.N\n" 为9字节
+        let records = RecordSet::new(&ips, &[]);
+        let mut format = RecordFormat::new(OutputFormat::Plain, &input, &[]).unwrap();
+
+        let (plans, oversized) = plan_max_bytes(&records, &format, 20);
+        assert_eq!(oversized, 0);
+        assert_eq!(plans, vec![vec![0..2], vec![2..4], vec![4..6], vec![6..8]]);
+
+        // 表头 "ip\n" 占3字节，每个文件只能再放一条记录
+        format.header = true;
+        let (plans, _) = plan_max_bytes(&records, &format, 20);
+        assert_eq!(plans.len(), 8);
+
+        // 单条记录超过上限时单独占一个文件
+        format.header = false;
+        let (plans, oversized) = plan_max_bytes(&records, &format, 5);
+        assert_eq!((plans.len(), oversized), (8, 8));
+    }
+
+    #[test]
+    fn whole_blocks_group_non_contiguous_networks() {
+        // 1.1.1.0/24 的两半被 2.2.2.0/24 隔开
+        let mut ips = Vec::new();
+        push_network(&mut ips, "1.1.1.0", 25);
+        push_network(&mut ips, "2.2.2.0", 24);
+        push_network(&mut ips, "1.1.1.128", 25);
+        let records = RecordSet::new(&ips, &[]);
+
+        let (plans, oversized) = plan_whole_blocks(&records, 300, 24);
+        assert_eq!(oversized, 0);
+        assert_eq!(plans, vec![vec![0..128, 384..512], vec![128..384]]);
+    }
+
+    #[test]
+    fn whole_blocks_pack_groups_and_count_oversized() {
+        let mut ips = Vec::new();
+        push_network(&mut ips, "10.0.0.0", 26);
+        push_network(&mut ips, "10.0.1.0", 26);
+        push_network(&mut ips, "10.0.2.0", 24);
+        push_network(&mut ips, "10.0.3.0", 26);
+        let records = RecordSet::new(&ips, &[]);
+
+        let (plans, oversized) = plan_whole_blocks(&records, 200, 24);
+        assert_eq!(oversized, 1);
+        assert_eq!(plans, vec![vec![0..128], vec![128..384], vec![384..448]]);
+    }
+}
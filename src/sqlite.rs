@@ -0,0 +1,138 @@
+use crate::generate::Record;
+use crate::output::{RecordFormat, RecordStats};
+use rusqlite::{params_from_iter, Connection, ToSql};
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+
+// 表名，分析时直接 SELECT ... FROM addresses
+pub const TABLE: &str = "addresses";
+
+// 每个事务插入的记录数，太小时提交次数多，太大时占用内存多
+const BATCH_SIZE: usize = 100_000;
+
+fn sqlite_error(err: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("SQLite 出错：{}", err))
+}
+
+// 标识符用双引号包裹，标签列名中的双引号写两次
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// 标签列不能与固定的列重名；SQLite 的列名不区分大小写，标签列之间也按不区分大小写比较
+// 在创建任何文件之前调用，避免写到一半才因为列名重复而失败
+pub fn check_columns(format: &RecordFormat) -> io::Result<()> {
+    let mut names = vec!["address", "ip", "cidr", "shard"];
+    if format.ports {
+        names.push("port");
+    }
+    let mut names: Vec<String> = names.into_iter().map(str::to_string).collect();
+    for name in &format.input().label_names {
+        if names.iter().any(|existing| existing.eq_ignore_ascii_case(name)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("标签列 {} 与 SQLite 表中的列名重复(列名不区分大小写)", name),
+            ));
+        }
+        names.push(name.clone());
+    }
+    Ok(())
+}
+
+// 建表语句：整数地址、文本地址、端口(有端口时)、来源CIDR、文件序号，以及输入中的全部标签列
+fn create_table(format: &RecordFormat) -> String {
+    let mut columns = vec![
+        "address INTEGER NOT NULL".to_string(),
+        "ip TEXT NOT NULL".to_string(),
+    ];
+    if format.ports {
+        columns.push("port INTEGER NOT NULL".to_string());
+    }
+    columns.push("cidr TEXT NOT NULL".to_string());
+    columns.push("shard INTEGER NOT NULL".to_string());
+    for name in &format.input().label_names {
+        columns.push(format!("{} TEXT", quote(name)));
+    }
+    format!("CREATE TABLE IF NOT EXISTS {} ({})", TABLE, columns.join(", "))
+}
+
+// 把记录插入 path 中的数据库，shard 为这个文件在输出中的序号(从1开始)
+// path 是还没有改名的临时文件，不需要日志和同步，提交时整个文件一起落盘；
+// 文件中已有数据(追加模式)时接着插入，地址索引在插入完成后才创建
+pub fn write_database<I: Iterator<Item = Record>>(
+    path: &Path,
+    records: I,
+    format: &RecordFormat,
+    shard: usize,
+) -> io::Result<RecordStats> {
+    check_columns(format)?;
+    let mut conn = Connection::open(path).map_err(sqlite_error)?;
+    conn.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")
+        .map_err(sqlite_error)?;
+    conn.execute(&create_table(format), []).map_err(sqlite_error)?;
+
+    let input = format.input();
+    let mut columns = vec!["address", "ip"];
+    if format.ports {
+        columns.push("port");
+    }
+    columns.extend(["cidr", "shard"]);
+    let labels: Vec<String> = input.label_names.iter().map(|name| quote(name)).collect();
+    columns.extend(labels.iter().map(String::as_str));
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        TABLE,
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let mut stats = RecordStats::default();
+    let mut records = records.peekable();
+    while records.peek().is_some() {
+        let transaction = conn.transaction().map_err(sqlite_error)?;
+        {
+            let mut statement = transaction.prepare_cached(&insert).map_err(sqlite_error)?;
+            for record in records.by_ref().take(BATCH_SIZE) {
+                let source = &input.records[record.source];
+                let address = record.ip;
+                let ip = Ipv4Addr::from(record.ip).to_string();
+                let cidr = source.network.to_string();
+                let shard = shard as i64;
+                let mut values: Vec<&dyn ToSql> = vec![&address, &ip];
+                if let Some(port) = &record.port {
+                    values.push(port);
+                }
+                values.push(&cidr);
+                values.push(&shard);
+                let label_values: Vec<&str> =
+                    (0..labels.len()).map(|label| input.label(source, label)).collect();
+                values.extend(label_values.iter().map(|value| value as &dyn ToSql));
+                statement.execute(params_from_iter(values)).map_err(sqlite_error)?;
+                stats.add(&record);
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+    }
+    conn.execute(
+        &format!("CREATE INDEX IF NOT EXISTS {0}_address ON {0} (address)", TABLE),
+        [],
+    )
+    .map_err(sqlite_error)?;
+    conn.close().map_err(|(_, err)| sqlite_error(err))?;
+    Ok(stats)
+}
+
+// 按写入顺序读出数据库中的全部地址和端口，校验时使用
+pub fn read_addresses(path: &Path, ports: bool) -> io::Result<Vec<(u32, Option<u16>)>> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(sqlite_error)?;
+    let port = if ports { "port" } else { "NULL" };
+    let query = format!("SELECT address, {} FROM {} ORDER BY rowid", port, TABLE);
+    let mut statement = conn.prepare(&query).map_err(sqlite_error)?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, Option<u16>>(1)?)))
+        .map_err(sqlite_error)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(sqlite_error)
+}
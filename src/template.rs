@@ -0,0 +1,156 @@
+use crate::dotted;
+use crate::generate::Record;
+use crate::input::InputSet;
+use crate::reverse;
+use ipnetwork::IpNetwork;
+use std::io::{self, Write};
+
+
+// 模板中的一段：原样输出的文本，或者一个占位符
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Ip,           // {ip}         点分十进制
+    Port,         // {port}       端口
+    Int,          // {int}        十进制u32
+    Hex,          // {hex}        8位十六进制
+    Reverse,      // {reverse}    反向解析名称
+    Cidr,         // {cidr}       来源CIDR
+    CidrIndex,    // {cidr_index} 在来源CIDR中的序号，从0开始
+    Index,        // {index}      在全部输出中的序号，从0开始
+    Label(usize), // {列名} 或 {label:列名}
+}
+
+// 每行输出的模板，例如 https://{ip}:{port}/cdn-cgi/trace
+// 在启动时解析并检查一次，写入时直接渲染到缓冲区
+#[derive(Clone, Debug)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Template {
+    // 解析模板，{{ 和 }} 表示字面的大括号；未知的占位符返回错误
+    pub fn parse(text: &str, input: &InputSet) -> io::Result<Template> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(invalid(format!("模板中的 {{{} 缺少 }}", name))),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Template::placeholder(name.trim(), input)?);
+                }
+                '}' => return Err(invalid(format!("模板中有多余的 }}：{}", text))),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template { segments })
+    }
+
+    fn placeholder(name: &str, input: &InputSet) -> io::Result<Segment> {
+        let segment = match name {
+            "ip" => Segment::Ip,
+            "port" => Segment::Port,
+            "int" => Segment::Int,
+            "hex" => Segment::Hex,
+            "reverse" => Segment::Reverse,
+            "cidr" => Segment::Cidr,
+            "cidr_index" => Segment::CidrIndex,
+            "index" => Segment::Index,
+            _ => {
+                let label = name.strip_prefix("label:").unwrap_or(name);
+                match input.label_index(label) {
+                    Some(index) => Segment::Label(index),
+                    None => {
+                        return Err(invalid(format!(
+                            "模板中未知的占位符：{{{}}}（可用：ip、port、int、hex、reverse、cidr、cidr_index、index，以及标签列：{}）",
+                            name,
+                            input.label_names.join(",")
+                        )))
+                    }
+                }
+            }
+        };
+        Ok(segment)
+    }
+
+    // 模板是否用到了端口
+    pub fn uses_port(&self) -> bool {
+        self.segments.contains(&Segment::Port)
+    }
+
+    // 模板是否用到了标签列
+    pub fn uses_labels(&self) -> bool {
+        self.segments.iter().any(|segment| matches!(segment, Segment::Label(_)))
+    }
+
+    // 把一条记录按模板渲染到缓冲区，不附加记录分隔符
+    pub fn render(&self, record: &Record, input: &InputSet, out: &mut Vec<u8>) {
+        let source = &input.records[record.source];
+        let value = record.ip;
+        for segment in &self.segments {
+            // 写入 Vec 不会失败
+            let _ = match segment {
+                Segment::Literal(text) => out.write_all(text.as_bytes()),
+                Segment::Ip => {
+                    dotted::push_ipv4(out, value);
+                    Ok(())
+                }
+                Segment::Port => {
+                    if let Some(port) = record.port {
+                        dotted::push_decimal(out, port as u64);
+                    }
+                    Ok(())
+                }
+                Segment::Int => {
+                    dotted::push_decimal(out, value as u64);
+                    Ok(())
+                }
+                Segment::Hex => {
+                    dotted::push_hex(out, value);
+                    Ok(())
+                }
+                Segment::Reverse => {
+                    reverse::push_reverse_name(record.ip, out);
+                    Ok(())
+                }
+                Segment::Cidr => write!(out, "{}", source.network),
+                Segment::CidrIndex => {
+                    let start = match source.network {
+                        IpNetwork::V4(network) => u32::from(network.network()),
+                        IpNetwork::V6(_) => value,
+                    };
+                    write!(out, "{}", value - start)
+                }
+                Segment::Index => write!(out, "{}", record.index),
+                Segment::Label(index) => out.write_all(input.label(source, *index).as_bytes()),
+            };
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use crate::cli::Options;
+use crate::columnar;
+use crate::input::InputSet;
+use crate::manifest::{self, FileEntry, Manifest, MANIFEST_FILE};
+use crate::output::OutputFormat;
+use crate::ranges::{self, Range4};
+use crate::reverse;
+use crate::sqlite;
+use ipnetwork::IpNetwork;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+
+// 每类问题最多列出的例子数
+const MAX_EXAMPLES: usize = 10;
+
+// 记录的比较键：高位为IP地址，低16位为端口(没有端口时为0)
+fn key(ip: u32, port: Option<u16>) -> u64 {
+    ((ip as u64) << 16) | port.unwrap_or(0) as u64
+}
+
+fn key_text(key: u64) -> String {
+    let ip = Ipv4Addr::from((key >> 16) as u32);
+    match key & 0xffff {
+        0 => ip.to_string(),
+        port => format!("{}:{}", ip, port),
+    }
+}
+
+// 某一类问题的计数和例子
+#[derive(Default)]
+struct Problems {
+    count: u64,
+    examples: Vec<String>,
+}
+
+impl Problems {
+    fn add(&mut self, count: u64, example: String) {
+        self.count += count;
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(example);
+        }
+    }
+
+    // 把问题写入 out，没有问题时返回 true
+    fn report<W: Write>(&self, out: &mut W, title: &str) -> io::Result<bool> {
+        if self.count == 0 {
+            return Ok(true);
+        }
+        writeln!(out, "{}：{}个", title, self.count)?;
+        for example in &self.examples {
+            writeln!(out, "  {}", example)?;
+        }
+        if self.count > self.examples.len() as u64 {
+            writeln!(out, "  ...")?;
+        }
+        Ok(false)
+    }
+}
+
+// 重新读取一个输出文件得到的结果
+struct FileCheck {
+    bytes: u64,
+    lines: u64,
+    sha256: String,
+    records: u64,
+    first: Option<Ipv4Addr>,
+    last: Option<Ipv4Addr>,
+}
+
+// 从一条记录的文本中解析出IP地址和端口，无法解析时返回 None
+fn parse_record(text: &str, params: &Options) -> Option<(u32, Option<u16>)> {
+    let has_port = !params.ports.is_empty();
+    let parse_port = |field: Option<&str>| -> Option<Option<u16>> {
+        if has_port {
+            field.and_then(|port| port.trim().parse::<u16>().ok()).map(Some)
+        } else {
+            Some(None)
+        }
+    };
+    match params.format {
+        OutputFormat::JsonLines => {
+            let value: Value = serde_json::from_str(text).ok()?;
+            let ip = value.get("ip")?.as_str()?.parse::<Ipv4Addr>().ok()?;
+            let port = match value.get("port") {
+                Some(port) => Some(u16::try_from(port.as_u64()?).ok()?),
+                None => None,
+            };
+            (port.is_some() == has_port).then_some((u32::from(ip), port))
+        }
+        OutputFormat::Plain => {
+            let first = text.split(params.separator.as_str()).next()?;
+            let (ip, port) = match first.split_once(':') {
+                Some((ip, port)) => (ip, Some(port)),
+                None => (first, None),
+            };
+            Some((u32::from(ip.trim().parse::<Ipv4Addr>().ok()?), parse_port(port)?))
+        }
+        OutputFormat::Csv | OutputFormat::Integer | OutputFormat::Hex => {
+            let mut fields = text.split(params.separator.as_str());
+            let first = fields.next()?.trim().trim_matches('"');
+            let ip = match params.format {
+                OutputFormat::Integer => first.parse::<u32>().ok()?,
+                OutputFormat::Hex => u32::from_str_radix(first, 16).ok()?,
+                _ => u32::from(first.parse::<Ipv4Addr>().ok()?),
+            };
+            Some((ip, parse_port(fields.next())?))
+        }
+        OutputFormat::Reverse => {
+            let first = text.split(params.separator.as_str()).next()?;
+            Some((u32::from(reverse::parse_reverse_name(first)?), None))
+        }
+        OutputFormat::Binary | OutputFormat::Sqlite | OutputFormat::Parquet => None,
+    }
+}
+
+// 读取一个输出文件，计算字节数、行数和SHA-256，并把解析出的记录放进 keys
+fn read_output_file(
+    path: &Path,
+    params: &Options,
+    keys: &mut Vec<u64>,
+    unparsed: &mut Problems,
+) -> io::Result<FileCheck> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut check = FileCheck {
+        bytes: 0,
+        lines: 0,
+        sha256: String::new(),
+        records: 0,
+        first: None,
+        last: None,
+    };
+    let mut add = |check: &mut FileCheck, ip: u32, port: Option<u16>| {
+        check.records += 1;
+        if check.first.is_none() {
+            check.first = Some(Ipv4Addr::from(ip));
+        }
+        check.last = Some(Ipv4Addr::from(ip));
+        keys.push(key(ip, port));
+    };
+
+    // SQLite 和 Parquet 文件按字节计算校验和，记录按写入顺序读出
+    if matches!(params.format, OutputFormat::Sqlite | OutputFormat::Parquet) {
+        let (bytes, lines, sha256) = manifest::digest_file(path)?;
+        let has_port = !params.ports.is_empty();
+        let records = match params.format {
+            OutputFormat::Parquet => columnar::read_addresses(path, has_port)?,
+            _ => sqlite::read_addresses(path, has_port)?,
+        };
+        for (ip, port) in records {
+            add(&mut check, ip, port);
+        }
+        check.bytes = bytes;
+        check.lines = lines;
+        check.sha256 = sha256;
+        return Ok(check);
+    }
+
+    if params.format == OutputFormat::Binary && params.template.is_none() {
+        let size = if params.ports.is_empty() { 4 } else { 6 };
+        let mut record = vec![0u8; size];
+        loop {
+            let mut filled = 0;
+            while filled < size {
+                let read = reader.read(&mut record[filled..])?;
+                if read == 0 {
+                    break;
+                }
+                filled += read;
+            }
+            hasher.update(&record[..filled]);
+            check.bytes += filled as u64;
+            check.lines += record[..filled].iter().filter(|&&byte| byte == b'\n').count() as u64;
+            if filled < size {
+                if filled > 0 {
+                    unparsed.add(1, format!("{}：末尾有{}个多余的字节", path.display(), filled));
+                }
+                break;
+            }
+            let ip = u32::from_be_bytes([record[0], record[1], record[2], record[3]]);
+            let port = (size == 6).then(|| u16::from_be_bytes([record[4], record[5]]));
+            add(&mut check, ip, port);
+        }
+        check.sha256 = manifest::to_hex(&hasher.finalize());
+        return Ok(check);
+    }
+
+    let ending = params.line_ending.as_bytes();
+    let delimiter = *ending.last().unwrap_or(&b'\n');
+    let skip_header = params.header
+        && params.template.is_none()
+        && params.format != OutputFormat::JsonLines;
+    let mut pending: Vec<u8> = Vec::new();
+    let mut chunk = Vec::new();
+    let mut first_record = true;
+    loop {
+        chunk.clear();
+        let read = reader.read_until(delimiter, &mut chunk)?;
+        if read > 0 {
+            hasher.update(&chunk);
+            check.bytes += read as u64;
+            check.lines += chunk.iter().filter(|&&byte| byte == b'\n').count() as u64;
+            pending.extend_from_slice(&chunk);
+            // 多字节的记录分隔符，读到最后一个字节时还要确认前面的字节也匹配
+            if !pending.ends_with(ending) && chunk.last() == Some(&delimiter) {
+                continue;
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+        let text = String::from_utf8_lossy(pending.strip_suffix(ending).unwrap_or(&pending)).into_owned();
+        pending.clear();
+        if std::mem::take(&mut first_record) && skip_header {
+            continue;
+        }
+        if params.template.is_some() {
+            check.records += 1;
+            continue;
+        }
+        match parse_record(&text, params) {
+            Some((ip, port)) => add(&mut check, ip, port),
+            None => unparsed.add(1, format!("{}：{}", path.display(), text)),
+        }
+    }
+    check.sha256 = manifest::to_hex(&hasher.finalize());
+    Ok(check)
+}
+
+// 比较清单中的记录和重新读取的结果，返回不一致的描述
+fn compare_entry(entry: &FileEntry, check: &FileCheck, params: &Options) -> Vec<String> {
+    let mut problems = Vec::new();
+    if entry.bytes != check.bytes {
+        problems.push(format!("大小 {} 字节，清单中为 {} 字节", check.bytes, entry.bytes));
+    }
+    if entry.lines != check.lines {
+        problems.push(format!("行数 {}，清单中为 {}", check.lines, entry.lines));
+    }
+    if entry.sha256 != check.sha256 {
+        problems.push("SHA-256 与清单不一致".to_string());
+    }
+    if entry.records != check.records {
+        problems.push(format!("记录数 {}，清单中为 {}", check.records, entry.records));
+    }
+    // 模板输出无法解析出IP地址，不比较首尾地址
+    if params.template.is_none() {
+        let first = check.first.map(|ip| ip.to_string());
+        let last = check.last.map(|ip| ip.to_string());
+        if entry.first != first || entry.last != last {
+            let show = |ip: &Option<String>| ip.clone().unwrap_or_else(|| "-".to_string());
+            problems.push(format!(
+                "首尾地址 {}~{}，清单中为 {}~{}",
+                show(&first),
+                show(&last),
+                show(&entry.first),
+                show(&entry.last)
+            ));
+        }
+    }
+    problems
+}
+
+// 生成应该存在的全部记录(已排序)
+fn expected_keys(networks: &[IpNetwork], ports: &[u16], excluded: &[Range4]) -> Vec<u64> {
+    let mut keys = Vec::new();
+    for network in networks {
+        if let IpNetwork::V4(network) = network {
+            for ip in network.iter() {
+                let ip = u32::from(ip);
+                if ranges::contains(excluded, ip) {
+                    continue;
+                }
+                if ports.is_empty() {
+                    keys.push(key(ip, None));
+                } else {
+                    keys.extend(ports.iter().map(|&port| key(ip, Some(port))));
+                }
+            }
+        }
+    }
+    keys.sort_unstable();
+    keys
+}
+
+// 按排序后的两组记录逐个比较，找出缺失、重复和超出范围的记录
+fn compare_keys(
+    expected: &[u64],
+    actual: &[u64],
+    missing: &mut Problems,
+    duplicated: &mut Problems,
+    out_of_range: &mut Problems,
+) {
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        let next = match (expected.get(i), actual.get(j)) {
+            (Some(&e), Some(&a)) => e.min(a),
+            (Some(&e), None) => e,
+            (None, Some(&a)) => a,
+            (None, None) => break,
+        };
+        let expected_count = expected[i..].iter().take_while(|&&k| k == next).count() as u64;
+        let actual_count = actual[j..].iter().take_while(|&&k| k == next).count() as u64;
+        i += expected_count as usize;
+        j += actual_count as usize;
+
+        if expected_count == 0 {
+            out_of_range.add(actual_count, key_text(next));
+        } else if actual_count > expected_count {
+            duplicated.add(
+                actual_count - expected_count,
+                format!("{}（出现{}次）", key_text(next), actual_count),
+            );
+        } else if actual_count < expected_count {
+            missing.add(expected_count - actual_count, key_text(next));
+        }
+    }
+}
+
+// 校验输出目录中的文件；input 为用来对比的输入，None 时使用清单中记录的CIDR
+// 全部一致时返回 true
+pub fn verify(dir: &Path, input: Option<&InputSet>) -> io::Result<bool> {
+    let manifest = Manifest::load(dir)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("在 {} 中找不到清单文件 {}", dir.display(), MANIFEST_FILE),
+        )
+    })?;
+    let params: Options = serde_json::from_value(manifest.parameters.clone()).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("清单中的运行参数无法解析：{}", e))
+    })?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut ok = manifest.complete;
+    if !manifest.complete {
+        writeln!(out, "清单标记为未完成：生成过程没有正常结束，输出文件可能不全")?;
+    }
+    let mut keys = Vec::new();
+    let mut unparsed = Problems::default();
+    let mut total_records = 0;
+    for entry in &manifest.files {
+        let path = dir.join(&entry.name);
+        let check = match read_output_file(&path, &params, &mut keys, &mut unparsed) {
+            Ok(check) => check,
+            Err(err) => {
+                writeln!(out, "不一致 {}：无法读取：{}", entry.name, err)?;
+                ok = false;
+                continue;
+            }
+        };
+        total_records += check.records;
+        let problems = compare_entry(entry, &check, &params);
+        if problems.is_empty() {
+            writeln!(out, "通过   {}（{}条记录）", entry.name, check.records)?;
+        } else {
+            ok = false;
+            writeln!(out, "不一致 {}：{}", entry.name, problems.join("；"))?;
+        }
+    }
+    if total_records != manifest.total_records {
+        ok = false;
+        writeln!(out, "记录总数 {}，清单中为 {}", total_records, manifest.total_records)?;
+    }
+
+    // 输入文件变化不影响输出是否完整，只给出提示
+    if let (Some(sha256), None) = (&manifest.input.sha256, input) {
+        if let Ok(content) = fs::read(&manifest.input.source) {
+            if manifest::to_hex(&Sha256::digest(content)) != *sha256 {
+                writeln!(out, "提示：输入文件 {} 在生成之后已被修改", manifest.input.source)?;
+            }
+        }
+    }
+
+    if params.template.is_some() {
+        writeln!(out, "提示：输出使用了模板，无法逐条解析地址，只校验了计数和校验和")?;
+    } else {
+        let networks: Vec<IpNetwork> = match input {
+            Some(input) => input.records.iter().map(|record| record.network).collect(),
+            None => manifest.input.parse_networks()?,
+        };
+        let excluded = manifest.input.parse_excluded()?;
+        let mut expected = expected_keys(&networks, &params.ports, &excluded);
+        if manifest.unique {
+            expected.dedup();
+        }
+        keys.sort_unstable();
+
+        let mut missing = Problems::default();
+        let mut duplicated = Problems::default();
+        let mut out_of_range = Problems::default();
+        compare_keys(&expected, &keys, &mut missing, &mut duplicated, &mut out_of_range);
+        ok &= unparsed.report(&mut out, "无法解析的记录")?;
+        ok &= missing.report(&mut out, "缺失的地址")?;
+        ok &= duplicated.report(&mut out, "重复的地址")?;
+        ok &= out_of_range.report(&mut out, "超出输入范围的地址")?;
+        writeln!(out, "应有{}条记录，实际读取{}条记录", expected.len(), keys.len())?;
+    }
+
+    writeln!(out, "{}", if ok { "校验通过" } else { "校验失败" })?;
+    Ok(ok)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(addresses: &[&str]) -> Vec<u64> {
+        let mut keys: Vec<u64> = addresses
+            .iter()
+            .map(|text| key(u32::from(text.parse::<Ipv4Addr>().unwrap()), None))
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    fn compare(expected: &[u64], actual: &[u64]) -> (Problems, Problems, Problems) {
+        let mut problems = (Problems::default(), Problems::default(), Problems::default());
+        compare_keys(expected, actual, &mut problems.0, &mut problems.1, &mut problems.2);
+        problems
+    }
+
+    #[test]
+    fn compare_keys_accepts_identical_records() {
+        let expected = keys(&["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        let (missing, duplicated, out_of_range) = compare(&expected, &expected);
+        assert_eq!((missing.count, duplicated.count, out_of_range.count), (0, 0, 0));
+    }
+
+    #[test]
+    fn compare_keys_finds_missing_duplicate_and_out_of_range() {
+        let expected = keys(&["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4"]);
+        let actual = keys(&["10.0.0.1", "10.0.0.1", "10.0.0.1", "10.0.0.3", "10.0.0.9"]);
+        let (missing, duplicated, out_of_range) = compare(&expected, &actual);
+        assert_eq!(missing.count, 2);
+        assert_eq!(missing.examples, ["10.0.0.2", "10.0.0.4"]);
+        assert_eq!(duplicated.count, 2);
+        assert_eq!(duplicated.examples, ["10.0.0.1（出现3次）"]);
+        assert_eq!(out_of_range.count, 1);
+        assert_eq!(out_of_range.examples, ["10.0.0.9"]);
+    }
+
+    #[test]
+    fn compare_keys_distinguishes_ports() {
+        let ip = u32::from(Ipv4Addr::new(10, 0, 0, 1));
+        let expected = vec![key(ip, Some(80)), key(ip, Some(443))];
+        let actual = vec![key(ip, Some(80)), key(ip, Some(8080))];
+        let (missing, duplicated, out_of_range) = compare(&expected, &actual);
+        assert_eq!(missing.examples, ["10.0.0.1:443"]);
+        assert_eq!(duplicated.count, 0);
+        assert_eq!(out_of_range.examples, ["10.0.0.1:8080"]);
+    }
+}
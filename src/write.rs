@@ -0,0 +1,139 @@
+use crate::atomic::{self, AtomicFile};
+use crate::cli::{Options, SplitMode};
+use crate::columnar;
+use crate::generate::{Record, RecordSet};
+use crate::manifest::{self, DigestWriter, Manifest};
+use crate::naming::FileNaming;
+use crate::output::{self, OutputFormat, RecordFormat};
+use crate::progress::Progress;
+use crate::split::{self, FilePlan};
+use crate::sqlite;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+
+// 将IP地址列表按输出格式写入文件
+// 先写入临时文件，完整写完后才改名为 output_file，并把文件记录到清单中
+// 清单中已有这个文件时(追加模式)，先复制原有内容再写入新的记录，不重复写表头
+// plan 为要写入的记录序号，写入的记录数计入 progress
+pub fn write_ips_to_file(
+    output_file: &Path,
+    ips: &RecordSet,
+    plan: &[Range<usize>],
+    format: &RecordFormat,
+    manifest: &mut Manifest,
+    progress: &Progress,
+) -> io::Result<()> {
+    if matches!(format.format, OutputFormat::Sqlite | OutputFormat::Parquet) {
+        let records = plan.iter().flat_map(|range| ips.range(range.start, range.end));
+        return write_ips_to_table_file(output_file, progress.track(records), format, manifest);
+    }
+    let previous = manifest.find_file(output_file).cloned();
+    let file = AtomicFile::create(output_file)?;
+    let mut writer = DigestWriter::new(BufWriter::new(file));
+    if previous.is_some() {
+        io::copy(&mut File::open(output_file)?, &mut writer)?;
+    }
+    let stats = output::write_records(&mut writer, ips, plan, format, previous.is_none(), Some(progress))?;
+    let (buffered, digest) = writer.finish();
+    let file = buffered.into_inner().map_err(|err| err.into_error())?;
+    file.commit()?;
+    manifest.add(output_file, &stats, digest, format.input());
+    if let Some(previous) = previous {
+        manifest.merge_previous(previous);
+    }
+    Ok(())
+}
+
+// SQLite 和 Parquet 格式：写入临时文件，写完后计算整个文件的校验和，再改名为 output_file
+// SQLite 的文件序号(shard 列)按清单中的顺序，追加到已有文件时沿用它原来的序号
+fn write_ips_to_table_file<I: Iterator<Item = Record>>(
+    output_file: &Path,
+    records: I,
+    format: &RecordFormat,
+    manifest: &mut Manifest,
+) -> io::Result<()> {
+    let previous = manifest.find_file(output_file).cloned();
+    let shard = match &previous {
+        Some(previous) => manifest.files.iter().position(|file| file.name == previous.name).unwrap_or(0),
+        None => manifest.files.len(),
+    };
+    let mut file = AtomicFile::create(output_file)?;
+    if previous.is_some() {
+        io::copy(&mut File::open(output_file)?, &mut file)?;
+    }
+    let temp = atomic::temp_path(output_file);
+    let stats = match format.format {
+        OutputFormat::Parquet => columnar::write_parquet(&temp, records, format, format.row_group_size)?,
+        _ => sqlite::write_database(&temp, records, format, shard + 1)?,
+    };
+    let digest = manifest::digest_file(&temp)?;
+    file.commit()?;
+    manifest.add(output_file, &stats, digest, format.input());
+    if let Some(previous) = previous {
+        manifest.merge_previous(previous);
+    }
+    Ok(())
+}
+
+// 每个文件的路径；skip 为目录中已有的文件数，追加时新文件的序号接在后面
+// 文件名模板中没有 {index}，或不同的标签整理成了相同的文件名时，多个文件会得到同一个路径，这时返回错误
+pub fn file_paths(
+    ips: &RecordSet,
+    plans: &[FilePlan],
+    format: &RecordFormat,
+    naming: &FileNaming,
+    options: &Options,
+    skip: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = match &options.split {
+        SplitMode::All => vec![naming.single(&options.output)],
+        _ => plans
+            .iter()
+            .enumerate()
+            .map(|(file_index, plan)| {
+                let first = plan.first().map(|range| ips.get(range.start));
+                naming.path(skip + file_index + 1, skip + plans.len(), first.as_ref(), format.input())
+            })
+            .collect(),
+    };
+    let mut seen = HashSet::new();
+    for path in &paths {
+        if !seen.insert(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("多个文件的路径相同：{}，请在 --file-name 中使用 {{index}}", path.display()),
+            ));
+        }
+    }
+    Ok(paths)
+}
+
+// 按分割计划写入文件，每个计划对应 paths 中的一个文件；有文件写入失败时返回 false
+pub fn write_plan(
+    ips: &RecordSet,
+    plans: &[FilePlan],
+    paths: &[PathBuf],
+    format: &RecordFormat,
+    manifest: &mut Manifest,
+    progress: &Progress,
+) -> bool {
+    let mut ok = true;
+    let total = plans.iter().map(split::plan_len).sum::<usize>();
+    progress.start_write(total as u64, plans.len() as u64);
+    for (plan, output_file) in plans.iter().zip(paths) {
+        // 调用函数写入txt文件中
+        if let Err(err) = write_ips_to_file(output_file, ips, plan, format, manifest, progress) {
+            progress.clear();
+            eprintln!("写入文件 {} 时出错：{}", output_file.display(), err);
+            ok = false;
+        } else {
+            progress.file_done();
+        }
+    }
+    progress.clear();
+    ok
+}
@@ -0,0 +1,123 @@
+// 导出格式的黄金文件测试：运行程序，把标准输出与 tests/golden 中的文件逐字节比较
+// 修改了导出格式时，设置环境变量 UPDATE_GOLDEN=1 运行测试可以重新生成黄金文件
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const OPTIONS: &[&str] = &[
+    "--set-name",
+    "blocklist",
+    "--timeout",
+    "1d2h",
+    "--comment",
+    "cdn ranges",
+    "--table",
+    "fw",
+    "--action",
+    "REJECT",
+];
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+fn export(target: &str, extra: &[&str]) -> String {
+    let input = tests_dir().join("fixtures").join("networks.txt");
+    let output = Command::new(env!("CARGO_BIN_EXE_generate_all_ipv4_addresses"))
+        .args(["export", "--target", target, "-i"])
+        .arg(&input)
+        .args(extra)
+        .output()
+        .expect("无法运行程序");
+    assert!(
+        output.status.success(),
+        "导出 {} 失败：{}",
+        target,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("导出的内容不是UTF-8")
+}
+
+// 扫描器格式有多个文件时写入输出目录，按文件名读回
+fn export_files(target: &str, names: &[&str]) -> Vec<String> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("export_{}", target));
+    let _ = fs::remove_dir_all(&dir);
+    export(target, &["--exclude-bogons", "-d", dir.to_str().expect("临时目录不是UTF-8")]);
+    names
+        .iter()
+        .map(|name| fs::read_to_string(dir.join(name)).expect("无法读取导出的文件"))
+        .collect()
+}
+
+fn check_golden(name: &str, actual: &str) {
+    let path = tests_dir().join("golden").join(format!("{}.txt", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).expect("无法写入黄金文件");
+        return;
+    }
+    let expected = fs::read_to_string(&path).expect("无法读取黄金文件");
+    assert_eq!(actual, expected, "{} 与黄金文件 {} 不一致", name, path.display());
+}
+
+#[test]
+fn ipset() {
+    check_golden("ipset", &export("ipset", &[]));
+    check_golden("ipset_options", &export("ipset", OPTIONS));
+}
+
+#[test]
+fn nftables() {
+    check_golden("nftables", &export("nftables", &[]));
+    check_golden("nftables_options", &export("nftables", OPTIONS));
+}
+
+#[test]
+fn iptables() {
+    check_golden("iptables", &export("iptables", &[]));
+    check_golden("iptables_options", &export("iptables", OPTIONS));
+}
+
+#[test]
+fn mikrotik() {
+    check_golden("mikrotik", &export("mikrotik", &[]));
+    check_golden("mikrotik_options", &export("mikrotik", OPTIONS));
+}
+
+#[test]
+fn masscan() {
+    check_golden("masscan", &export("masscan", &["--ports", "80,443,8000-8010", "--exclude-bogons"]));
+}
+
+#[test]
+fn nmap() {
+    let files = export_files("nmap", &["nmap_targets.txt", "nmap_exclude.txt"]);
+    check_golden("nmap_targets", &files[0]);
+    check_golden("nmap_exclude", &files[1]);
+}
+
+#[test]
+fn zmap() {
+    let files = export_files("zmap", &["zmap_whitelist.txt", "zmap_blacklist.txt"]);
+    check_golden("zmap_whitelist", &files[0]);
+    check_golden("zmap_blacklist", &files[1]);
+}
+
+#[test]
+fn firewall_excludes_bogons() {
+    check_golden("ipset_excluded", &export("ipset", &["--exclude-bogons"]));
+}
+
+#[test]
+fn nginx() {
+    for target in ["nginx-realip", "nginx-geo", "nginx-allow", "nginx-deny"] {
+        let name = target.replace('-', "_");
+        check_golden(&name, &export(target, &[]));
+        check_golden(&format!("{}_options", name), &export(target, OPTIONS));
+    }
+}
+
+#[test]
+fn haproxy() {
+    check_golden("haproxy", &export("haproxy", &[]));
+    check_golden("haproxy_options", &export("haproxy", OPTIONS));
+}
@@ -0,0 +1,48 @@
+// 地址集合文件的往返测试：建立集合，求并集、交集，再转换回CIDR和查询地址
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_generate_all_ipv4_addresses"))
+        .args(args)
+        .output()
+        .expect("无法运行程序")
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(output.status.success(), "{:?} 失败：{}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).expect("输出的内容不是UTF-8")
+}
+
+fn set_path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_str().expect("临时目录不是UTF-8").to_string()
+}
+
+#[test]
+fn round_trip() {
+    let dir: PathBuf = Path::new(env!("CARGO_TARGET_TMPDIR")).join("set_round_trip");
+    std::fs::create_dir_all(&dir).expect("无法创建临时目录");
+    let a = set_path(&dir, "a.roaring");
+    let b = set_path(&dir, "b.roaring");
+    let union = set_path(&dir, "union.roaring");
+    let intersection = set_path(&dir, "intersection.roaring");
+
+    stdout(&["set", "build", "10.0.0.0/8", "1.1.1.0/24", "--exclude", "10.1.0.0/16", "-o", &a]);
+    stdout(&["set", "build", "10.0.0.0/12", "8.8.8.0/30", "-o", &b]);
+    stdout(&["set", "union", &a, &b, "-o", &union]);
+    stdout(&["set", "intersect", &a, &b, "-o", &intersection]);
+
+    assert_eq!(stdout(&["set", "cidrs", &union]), "1.1.1.0/24\n8.8.8.0/30\n10.0.0.0/8\n");
+    assert_eq!(
+        stdout(&["set", "cidrs", &intersection]),
+        "10.0.0.0/16\n10.2.0.0/15\n10.4.0.0/14\n10.8.0.0/13\n"
+    );
+    assert_eq!(stdout(&["set", "text", &b]).lines().count(), 1_048_580);
+    // 一个 /8 去掉一个 /16 只需要几KB
+    assert!(std::fs::metadata(&a).expect("找不到集合文件").len() < 8 * 1024);
+
+    let contains = run(&["set", "contains", &intersection, "10.2.0.1", "10.1.2.3"]);
+    assert_eq!(String::from_utf8_lossy(&contains.stdout), "10.2.0.1 是\n10.1.2.3 否\n");
+    assert_eq!(contains.status.code(), Some(1));
+}